@@ -1,51 +1,497 @@
 use std::env;
+use std::io::Write;
 
 use fvtree;
 
-fn main() {
-    //env::set_var("RUST_BACKTRACE", "1");
-    let args: Vec<String> = env::args().collect();
+//Returns the argument following flag, None if there's no flag,
+//or exits if the flag is there but nothing follows it.
+fn arg_following(args: &[String], flag: &str, expected: &str) -> Option<String> {
+    let idx = args.iter().position(|s| s == flag)?;
 
-    let fvtree_string;
-    let index_option = args.iter().position(|s| s == "-i");
+    if idx + 1 > args.len() - 1 {
+        eprintln!("{} was enabled, but no {} followed it.", flag, expected);
+        std::process::exit(-1);
+    }
 
-    //If there is a -i, validate the tree_string following it at idx + 1.
-    if index_option.is_some() {
-        let idx = index_option.unwrap();
+    Some(args[idx + 1].clone())
+}
 
-        if idx + 1 > args.len() - 1 {
-            eprintln!("-i was enabled, but no tree_string followed it.");
+fn exit_on_err<T>(result: Result<T, &'static str>) -> T {
+    match result {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("{}", e);
             std::process::exit(-1);
+        },
+    }
+}
+
+//Columns and rows of the terminal, from $COLUMNS and $LINES if they're set,
+//then from stty, or 80x24 if neither knows.
+fn terminal_size() -> (usize, usize) {
+    let from_env = |var| env::var(var).ok().and_then(|s| s.parse::<usize>().ok());
+    if let (Some(columns), Some(lines)) = (from_env("COLUMNS"), from_env("LINES")) {
+        return (columns, lines);
+    }
+
+    //stty reports on whatever its stdin is, so it needs ours.
+    let from_stty = std::process::Command::new("stty")
+        .arg("size")
+        .stdin(std::process::Stdio::inherit())
+        .output()
+        .ok()
+        .and_then(|output| {
+            let size = String::from_utf8(output.stdout).ok()?;
+            let mut size = size.split_whitespace().map(|s| s.parse::<usize>());
+            match (size.next(), size.next()) {
+                (Some(Ok(lines)), Some(Ok(columns))) => Some((columns, lines)),
+                _ => None,
+            }
+        });
+
+    from_stty.unwrap_or((80, 24))
+}
+
+//-f picks the fruit tree Config instead of the default.
+fn config(args: &[String]) -> fvtree::Config {
+    if args.iter().any(|s| s == "-f") {
+        fvtree::Config::new_fruit_tree()
+    }
+    else {
+        fvtree::Config::new()
+    }
+}
+
+//fvt index <number> renders the tree with that number,
+//fvt index --of <tree_string> prints the number of a tree.
+//-f numbers fruit trees instead of the default.
+fn index(args: &[String]) {
+    let conf = config(args);
+
+    if let Some(input_tree_string) = arg_following(args, "--of", "tree_string") {
+        let fvtree_string = exit_on_err(fvtree::FvtreeString::build(&input_tree_string));
+        println!("{}", exit_on_err(fvtree_string.index(&conf)));
+        return;
+    }
+
+    let index = match arg_following(args, "index", "number") {
+        Some(number) => exit_on_err(number.parse::<fvtree::BigUint>()),
+        None => return,
+    };
+    let fvtree_string = exit_on_err(fvtree::FvtreeString::from_index(&index, &conf));
+
+    if args.iter().any(|s| s == "-t") {
+        println!("{}", fvtree_string);
+    }
+    let fvtree = exit_on_err(fvtree::Fvtree::build(&fvtree_string));
+    print!("{}", fvtree);
+}
+
+//fvt count <max_len> prints how many trees there are of each length up to max_len,
+//-l lists every one of them instead.
+//--sticks counts, or lists, the trees with up to max_len sticks instead, however long they are.
+//-f counts fruit trees instead of the default.
+fn count(args: &[String]) {
+    let conf = config(args);
+
+    let max_len = match arg_following(args, "count", "max_len") {
+        Some(max_len) => exit_on_err(max_len.parse::<usize>().map_err(|_| "max_len should be a number.")),
+        None => return,
+    };
+
+    let by_sticks = args.iter().any(|s| s == "--sticks");
+
+    if args.iter().any(|s| s == "-l") {
+        let fvtree_strings = if by_sticks {
+            exit_on_err(fvtree::FvtreeString::enumerate_up_to_sticks(max_len, &conf))
         }
         else {
-            let input_tree_string: String = args[idx + 1].clone();
-
-            match fvtree::FvtreeString::build(&input_tree_string) {
-                Ok(ts) => fvtree_string = ts,
-                Err(e) => {
-                    eprintln!("{}", e);
-                    std::process::exit(-1);
-                },
-            }
+            fvtree::FvtreeString::enumerate(max_len, &conf)
+        };
+        for fvtree_string in fvtree_strings {
+            println!("{}", fvtree_string);
         }
+        return;
+    }
+
+    let counts = if by_sticks {
+        exit_on_err(fvtree::FvtreeString::count_up_to_sticks(max_len, &conf))
     }
-    //There is no -i, generate randomly.
     else {
-        fvtree_string = fvtree::FvtreeString::new();
+        fvtree::FvtreeString::count_up_to(max_len, &conf)
+    };
+    let mut total = fvtree::BigUint::zero();
+    for (len, count) in counts.iter().enumerate() {
+        total = &total + count;
+        println!("{}: {}", len, count);
+    }
+    println!("Total: {}", total);
+}
+
+//fvt age <days> grows the tree a little each day, and prints the tree string it grows into,
+//with the same options for which tree to start from as fvt on its own.
+//-f grows it as a fruit tree instead of the default.
+fn age(args: &[String]) {
+    let conf = config(args);
+
+    let days = match arg_following(args, "age", "days") {
+        Some(days) => exit_on_err(days.parse::<usize>().map_err(|_| "days should be a number.")),
+        None => return,
+    };
+
+    let mut rng = rand::thread_rng();
+    let mut fvtree_string = fvtree_string(args);
+    for _ in 0..days {
+        exit_on_err(fvtree_string.grow(&mut rng, &conf));
+    }
+
+    println!("{}", fvtree_string);
+}
+
+//fvt edit cuts the tree up, and prints the tree string left over,
+//with the same options for which tree to start from as fvt on its own.
+//--prune <n> cuts off the nth branch, counting from 0 in the order they come in the tree string.
+//--graft <n> swaps the nth branch for a branch of the tree string after --scion,
+//the first one or the one after --scion-branch.
+//--truncate <height> cuts off everything higher than height above the root.
+//--strip-leaves takes off every leaf.
+//--mirror turns it to face the other way, --scale-up makes it twice the size,
+//and --lean <percent> leans it over to the right, or the left if percent is negative.
+//--canonicalize takes out sticks that don't change how it looks and puts its branches in order.
+//They're done in that order, and -p renders what's left as well.
+fn edit(args: &[String]) {
+    let mut fvtree_string = fvtree_string(args);
+
+    if let Some(n) = arg_following(args, "--prune", "branch") {
+        let n = exit_on_err(n.parse::<usize>().map_err(|_| "The branch to prune should be a number."));
+        fvtree_string = exit_on_err(fvtree_string.prune(n));
+    }
+    if let Some(n) = arg_following(args, "--graft", "branch") {
+        let n = exit_on_err(n.parse::<usize>().map_err(|_| "The branch to graft onto should be a number."));
+        let scion = match arg_following(args, "--scion", "tree_string") {
+            Some(scion) => exit_on_err(fvtree::FvtreeString::build(&scion)),
+            None => exit_on_err(Err("--graft needs a tree string to graft from after --scion.")),
+        };
+        let m = match arg_following(args, "--scion-branch", "branch") {
+            Some(m) => exit_on_err(m.parse::<usize>().map_err(|_| "The branch to graft from should be a number.")),
+            None => 0,
+        };
+        fvtree_string = exit_on_err(fvtree_string.graft(n, &scion, m));
+    }
+    if let Some(height) = arg_following(args, "--truncate", "height") {
+        let height = exit_on_err(height.parse::<usize>().map_err(|_| "height should be a number."));
+        fvtree_string = exit_on_err(fvtree_string.truncate(height));
     }
+    if args.iter().any(|s| s == "--strip-leaves") {
+        fvtree_string = fvtree_string.strip_leaves();
+    }
+    if args.iter().any(|s| s == "--mirror") {
+        fvtree_string = fvtree_string.mirror();
+    }
+    if args.iter().any(|s| s == "--scale-up") {
+        fvtree_string = fvtree_string.scale_up();
+    }
+    if let Some(percent) = arg_following(args, "--lean", "percent") {
+        let percent = exit_on_err(percent.parse::<i32>().map_err(|_| "percent should be a number."));
+        fvtree_string = exit_on_err(fvtree_string.lean(percent));
+    }
+    if args.iter().any(|s| s == "--canonicalize") {
+        fvtree_string = exit_on_err(fvtree_string.canonicalize());
+    }
+
+    println!("{}", fvtree_string);
+    if args.iter().any(|s| s == "-p") {
+        let fvtree = exit_on_err(fvtree::Fvtree::build_in_season(&fvtree_string, season(args)));
+        print!("{}", fvtree);
+    }
+}
+
+//fvt breed --with <tree_string> crosses the tree with another, and prints their two children,
+//with the same options for which tree to start from as fvt on its own.
+//--rate <n> mutates each branch and leaf of the children n times out of 100, 5 by default.
+//-p renders them as well.
+fn breed(args: &[String]) {
+    let fvtree_string = fvtree_string(args);
+    let other = match arg_following(args, "--with", "tree_string") {
+        Some(other) => exit_on_err(fvtree::FvtreeString::build(&other)),
+        None => exit_on_err(Err("breed needs a tree string to cross with after --with.")),
+    };
+    let rate = match arg_following(args, "--rate", "rate") {
+        Some(rate) => exit_on_err(rate.parse::<usize>().map_err(|_| "rate should be a number.")),
+        None => 5,
+    };
+
+    let mut rng = rand::thread_rng();
+    let (child_a, child_b) = exit_on_err(fvtree::FvtreeString::crossover(&fvtree_string, &other, &mut rng));
+
+    for child in [child_a, child_b] {
+        let child = child.mutate(&mut rng, rate);
+        println!("{}", child);
+        if args.iter().any(|s| s == "-p") {
+            let fvtree = exit_on_err(fvtree::Fvtree::build_in_season(&child, season(args)));
+            print!("{}", fvtree);
+        }
+    }
+}
+
+//fvt diff --with <tree_string> prints the branches and leaflets taken out of the tree and put
+//into the other one, and how much they look alike, from 0 to 1,
+//with the same options for which tree to start from as fvt on its own.
+//-p renders both on top of each other as well, marking where they differ.
+fn diff(args: &[String]) {
+    let fvtree_string = fvtree_string(args);
+    let other = match arg_following(args, "--with", "tree_string") {
+        Some(other) => exit_on_err(fvtree::FvtreeString::build(&other)),
+        None => exit_on_err(Err("diff needs a tree string to compare with after --with.")),
+    };
+
+    for difference in exit_on_err(fvtree_string.diff(&other)) {
+        let change = match difference.change {
+            fvtree::Change::Added => "+",
+            fvtree::Change::Removed => "-",
+        };
+        let subtree = match difference.subtree {
+            fvtree::Subtree::Branch => "branch",
+            fvtree::Subtree::Leaflet => "leaflet",
+        };
+        println!("{} {} at ({}, {}): {}", change, subtree, difference.at.x, difference.at.y, difference.tree_string);
+    }
+    println!("similarity: {:.3}", exit_on_err(fvtree_string.similarity(&other)));
+
+    if args.iter().any(|s| s == "-p") {
+        print!("{}", exit_on_err(fvtree::Fvtree::diff(&fvtree_string, &other)));
+    }
+}
+
+//fvt trace <file> works out the tree string for a tree drawn in the file, or read in if there's
+//no file, and prints it, then every cell where the tree it's worked out doesn't look like the
+//drawing, with the root at (0, 0), and what's there in each.
+//-p renders the tree it's worked out as well.
+fn trace(args: &[String]) {
+    let art = match args.get(2).filter(|arg| !arg.starts_with('-')) {
+        Some(file) => exit_on_err(std::fs::read_to_string(file).map_err(|_| "Couldn't read the file.")),
+        None => {
+            let mut art = String::new();
+            exit_on_err(std::io::Read::read_to_string(&mut std::io::stdin(), &mut art).map_err(|_| "Couldn't read the tree in."));
+            art
+        },
+    };
+
+    let tracing = exit_on_err(fvtree::FvtreeString::trace(&art));
+    println!("{}", tracing.fvtree_string);
+    for (point, drawn, traced) in tracing.differences {
+        println!("differs: ({}, {}) drawn '{}' traced '{}'", point.x, point.y, drawn.unwrap_or(' '), traced.unwrap_or(' '));
+    }
+
+    if args.iter().any(|s| s == "-p") {
+        let fvtree = exit_on_err(fvtree::Fvtree::build_in_season(&tracing.fvtree_string, season(args)));
+        print!("{}", fvtree);
+    }
+}
+
+//fvt species lists every species --species can grow.
+fn species() {
+    for (name, description) in fvtree::SPECIES {
+        println!("{}: {}", name, description);
+    }
+}
+
+//The tree the arguments ask for, read in or grown.
+fn fvtree_string(args: &[String]) -> fvtree::FvtreeString {
+    let fvtree_string;
+
+    //If there is a -i, validate the tree_string following it.
+    if let Some(input_tree_string) = arg_following(args, "-i", "tree_string") {
+        fvtree_string = exit_on_err(fvtree::FvtreeString::build(&input_tree_string));
+    }
+    //If there is a -k, decode the compact code following it.
+    else if let Some(input_code) = arg_following(args, "-k", "compact code") {
+        fvtree_string = exit_on_err(fvtree::FvtreeString::from_compact_code(&input_code));
+    }
+    //If there is a --lsystem, grow the preset or L-system file following it,
+    //for as many generations as follow -g, or 4.
+    else if let Some(lsystem) = arg_following(args, "--lsystem", "preset or L-system file") {
+        let lsystem = match fvtree::LSystem::preset(&lsystem) {
+            Some(preset) => preset,
+            None => exit_on_err(fvtree::LSystem::from_file(&lsystem)),
+        };
+        let generations = match arg_following(args, "-g", "generations") {
+            Some(generations) => exit_on_err(generations.parse::<usize>().map_err(|_| "generations should be a number.")),
+            None => 4,
+        };
+
+        fvtree_string = exit_on_err(fvtree::FvtreeString::new_lsystem(&lsystem, generations));
+    }
+    //If there is a --crown, grow towards points scattered through an ellipse, cone or umbrella.
+    else if let Some(crown) = arg_following(args, "--crown", "crown shape") {
+        let mut conf = fvtree::ColonizationConfig::new();
+        conf.crown = exit_on_err(crown.parse::<fvtree::CrownShape>());
+
+        fvtree_string = fvtree::FvtreeString::new_colonization(&conf);
+    }
+    //If there is a --species, grow a tree of that species, see fvt species.
+    else if let Some(species) = arg_following(args, "--species", "species") {
+        fvtree_string = exit_on_err(fvtree::FvtreeString::generate_species(&species));
+    }
+    //There is no -i, -k, --lsystem, --crown or --species, generate randomly.
+    //-u <len> picks uniformly among every tree len long, rather than growing one stick by stick.
+    //It can't be used with -a, --symmetric, --silhouette or --fit-terminal.
+    //-f generates a fruit tree instead of the default.
+    //-a keeps sticks from landing on each other where it can.
+    //--symmetric mirrors the branches on either side of the trunk.
+    //--silhouette <shape> keeps the tree inside a round, columnar, conical or weeping outline,
+    //e.g. "round", or "round:25x16" for a particular width and height.
+    //--fit-terminal keeps the rendered tree small enough for the terminal, with a line to spare for the prompt.
+    else {
+        let mut conf = config(args);
+        conf.t.avoid_collisions = args.iter().any(|s| s == "-a");
+        conf.t.symmetric = args.iter().any(|s| s == "--symmetric");
+        if let Some(silhouette) = arg_following(args, "--silhouette", "silhouette") {
+            conf.t.silhouette = Some(exit_on_err(silhouette.parse::<fvtree::Silhouette>()));
+        }
+        if args.iter().any(|s| s == "--fit-terminal") {
+            let (columns, lines) = terminal_size();
+            conf.t.max_width = Some(columns);
+            conf.t.max_height = Some(lines.saturating_sub(1));
+        }
+
+        fvtree_string = match arg_following(args, "-u", "len") {
+            Some(len) => {
+                let len = exit_on_err(len.parse::<usize>().map_err(|_| "len should be a number."));
+                exit_on_err(fvtree::FvtreeString::new_uniform_from_config(len, &conf))
+            },
+            None => fvtree::FvtreeString::new_recursive_from_config(&conf),
+        };
+    }
+
+    fvtree_string
+}
+
+//--season <season> renders the tree as it looks in spring, summer, autumn or winter.
+fn season(args: &[String]) -> fvtree::Season {
+    match arg_following(args, "--season", "season") {
+        Some(season) => exit_on_err(season.parse::<fvtree::Season>()),
+        None => fvtree::Season::Summer,
+    }
+}
+
+//fvt grow animates the tree growing, with the same options for which tree as fvt on its own.
+//-n <sticks> puts down that many sticks a frame, 1 otherwise.
+//--delay <ms> waits that long between frames, 100 otherwise.
+//--export prints every frame as text instead, each followed by a line with only a form feed on it.
+fn grow(args: &[String]) {
+    let fvtree_string = fvtree_string(args);
+    let sticks_per_frame = match arg_following(args, "-n", "sticks") {
+        Some(sticks) => exit_on_err(sticks.parse::<usize>().map_err(|_| "sticks should be a number.")),
+        None => 1,
+    };
+    let delay = match arg_following(args, "--delay", "ms") {
+        Some(delay) => exit_on_err(delay.parse::<u64>().map_err(|_| "ms should be a number.")),
+        None => 100,
+    };
+    let growth = exit_on_err(fvtree::Fvtree::grow(&fvtree_string, season(args), sticks_per_frame));
+
+    if args.iter().any(|s| s == "--export") {
+        print!("{}", growth.into_text());
+        return;
+    }
+
+    play(growth, delay);
+}
+
+//Clears the screen, then draws each frame over the last from the top left, clearing the rest of
+//each line as it goes, delay ms apart.
+fn play(frames: impl Iterator<Item = fvtree::Fvtree>, delay: u64) {
+    let mut stdout = std::io::stdout();
+    print!("\x1b[2J");
+    for frame in frames {
+        print!("\x1b[H");
+        for line in frame.to_string().lines() {
+            println!("{}\x1b[K", line);
+        }
+        let _ = stdout.flush();
+        std::thread::sleep(std::time::Duration::from_millis(delay));
+    }
+}
+
+//fvt sway blows the tree about in the wind until it's stopped, for an idle screen,
+//with the same options for which tree as fvt on its own, and --season.
+//--strength <cells> is how far the top of the tree sways either way, 1 otherwise.
+//--frames <frames> stops after that many frames.
+//--delay <ms> waits that long between frames, 150 otherwise.
+fn sway(args: &[String]) {
+    let fvtree_string = fvtree_string(args);
+    let fvtree = exit_on_err(fvtree::Fvtree::build_in_season(&fvtree_string, season(args)));
+
+    let mut conf = fvtree::WindConfig::new();
+    if let Some(strength) = arg_following(args, "--strength", "cells") {
+        conf.strength = exit_on_err(strength.parse::<usize>().map_err(|_| "cells should be a number."));
+    }
+    let delay = match arg_following(args, "--delay", "ms") {
+        Some(delay) => exit_on_err(delay.parse::<u64>().map_err(|_| "ms should be a number.")),
+        None => 150,
+    };
+
+    match arg_following(args, "--frames", "frames") {
+        Some(frames) => {
+            let frames = exit_on_err(frames.parse::<usize>().map_err(|_| "frames should be a number."));
+            play(fvtree.sway(&conf, rand::thread_rng()).take(frames), delay);
+        },
+        None => play(fvtree.sway(&conf, rand::thread_rng()), delay),
+    }
+}
+
+//fvt on its own prints the tree string of the tree the arguments ask for, see fvtree_string(),
+//or whichever of these are asked for instead:
+//-t the tree string, -s the tree string shortened, -c its compact code,
+//--hash a hash of its canonical form, and -p the rendered tree.
+fn tree(args: &[String]) {
+    let fvtree_string = fvtree_string(args);
 
     let print_tree_string: bool = args.iter().any(|s| s == "-t");
+    let print_compact_tree_string: bool = args.iter().any(|s| s == "-s");
+    let print_compact_code: bool = args.iter().any(|s| s == "-c");
+    let print_hash: bool = args.iter().any(|s| s == "--hash");
     let print_fvtree: bool = args.iter().any(|s| s == "-p");
+    let season = season(args);
 
     if print_tree_string {
         println!("{}", fvtree_string);
     }
+    if print_compact_tree_string {
+        println!("{}", fvtree_string.compact());
+    }
+    if print_compact_code {
+        println!("{}", fvtree_string.to_compact_code());
+    }
+    if print_hash {
+        println!("{:016x}", exit_on_err(fvtree_string.canonicalize()).content_hash());
+    }
     if print_fvtree {
-        let fvtree = fvtree::Fvtree::build(&fvtree_string).unwrap();
-        print!("{}", fvtree.to_string());
+        let fvtree = fvtree::Fvtree::build_in_season(&fvtree_string, season).unwrap();
+        print!("{}", fvtree);
     }
 
-    if !print_tree_string && !print_fvtree {
+    if !print_tree_string && !print_compact_tree_string && !print_compact_code && !print_hash && !print_fvtree {
         println!("{}", fvtree_string);
     }
 }
+
+fn main() {
+    //env::set_var("RUST_BACKTRACE", "1");
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("index") => index(&args),
+        Some("count") => count(&args),
+        Some("species") => species(),
+        Some("age") => age(&args),
+        Some("edit") => edit(&args),
+        Some("breed") => breed(&args),
+        Some("diff") => diff(&args),
+        Some("trace") => trace(&args),
+        Some("grow") => grow(&args),
+        Some("sway") => sway(&args),
+        _ => tree(&args),
+    }
+}
@@ -0,0 +1,475 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Sub};
+use std::str::FromStr;
+
+use rand::Rng;
+
+//Just enough of an arbitrary precision unsigned integer to count trees with.
+//There are far more trees than fit in a u128, even short ones.
+//
+//Stored as base 2^32 digits ("limbs"), least significant first, without leading zeros,
+//so zero has no limbs at all.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+const LIMB_BITS: u32 = 32;
+
+impl BigUint {
+    pub fn zero() -> BigUint {
+        BigUint {limbs: Vec::new()}
+    }
+
+    pub fn one() -> BigUint {
+        BigUint {limbs: vec![1]}
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn normalize(mut self) -> BigUint {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        self
+    }
+
+    //The number of bits needed to write it down, 0 for zero.
+    pub fn bits(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() * LIMB_BITS as usize - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.limbs.len() > 4 {
+            return None;
+        }
+
+        let mut n: u128 = 0;
+        for limb in self.limbs.iter().rev() {
+            n = n << LIMB_BITS | *limb as u128;
+        }
+        Some(n)
+    }
+
+    pub fn mul_small(&self, m: u32) -> BigUint {
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+        let mut carry: u64 = 0;
+
+        for limb in &self.limbs {
+            let t = *limb as u64 * m as u64 + carry;
+            limbs.push(t as u32);
+            carry = t >> LIMB_BITS;
+        }
+        limbs.push(carry as u32);
+
+        BigUint {limbs}.normalize()
+    }
+
+    pub fn add_small(&self, a: u32) -> BigUint {
+        self + &BigUint::from(a as u64)
+    }
+
+    //Returns the quotient and the remainder.
+    pub fn div_rem_small(&self, d: u32) -> (BigUint, u32) {
+        if d == 0 {
+            panic!("Attempted to divide a BigUint by zero.");
+        }
+
+        let mut limbs = vec![0; self.limbs.len()];
+        let mut rem: u64 = 0;
+
+        for i in (0..self.limbs.len()).rev() {
+            let t = rem << LIMB_BITS | self.limbs[i] as u64;
+            limbs[i] = (t / d as u64) as u32;
+            rem = t % d as u64;
+        }
+
+        (BigUint {limbs}.normalize(), rem as u32)
+    }
+
+    //Returns the quotient and the remainder.
+    //Long division, Knuth's Algorithm D, as laid out in Hacker's Delight (divmnu).
+    pub fn div_rem(&self, divisor: &BigUint) -> (BigUint, BigUint) {
+        if divisor.is_zero() {
+            panic!("Attempted to divide a BigUint by zero.");
+        }
+        if self < divisor {
+            return (BigUint::zero(), self.clone());
+        }
+        if divisor.limbs.len() == 1 {
+            let (q, r) = self.div_rem_small(divisor.limbs[0]);
+            return (q, BigUint::from(r as u64));
+        }
+
+        let n = divisor.limbs.len();
+        let m = self.limbs.len();
+        let b: u64 = 1 << LIMB_BITS;
+
+        //Normalize so the divisor's top limb has its high bit set,
+        //which keeps the estimates of each quotient limb off by at most 2.
+        let s = divisor.limbs[n - 1].leading_zeros();
+        let vn = shift_left(&divisor.limbs, s, n);
+        let mut un = shift_left(&self.limbs, s, m + 1);
+
+        let mut q = vec![0u32; m - n + 1];
+
+        for j in (0..=m - n).rev() {
+            let num = (un[j + n] as u64) << LIMB_BITS | un[j + n - 1] as u64;
+            let mut qhat = num / vn[n - 1] as u64;
+            let mut rhat = num % vn[n - 1] as u64;
+
+            while qhat >= b || qhat * vn[n - 2] as u64 > (rhat << LIMB_BITS | un[j + n - 2] as u64) {
+                qhat -= 1;
+                rhat += vn[n - 1] as u64;
+                if rhat >= b {
+                    break;
+                }
+            }
+
+            //Multiply and subtract.
+            let mut k: i64 = 0;
+            let mut t: i64;
+            for i in 0..n {
+                let p = qhat * vn[i] as u64;
+                t = un[i + j] as i64 - k - (p & 0xffff_ffff) as i64;
+                un[i + j] = t as u32;
+                k = (p >> LIMB_BITS) as i64 - (t >> LIMB_BITS);
+            }
+            t = un[j + n] as i64 - k;
+            un[j + n] = t as u32;
+
+            q[j] = qhat as u32;
+
+            //Subtracted too much, add one divisor back.
+            if t < 0 {
+                q[j] = q[j].wrapping_sub(1);
+                let mut carry: u64 = 0;
+                for i in 0..n {
+                    let t = un[i + j] as u64 + vn[i] as u64 + carry;
+                    un[i + j] = t as u32;
+                    carry = t >> LIMB_BITS;
+                }
+                un[j + n] = un[j + n].wrapping_add(carry as u32);
+            }
+        }
+
+        //Unnormalize the remainder.
+        let mut r = vec![0u32; n];
+        for i in 0..n {
+            r[i] = if s == 0 {
+                un[i]
+            }
+            else {
+                un[i] >> s | un[i + 1] << (LIMB_BITS - s)
+            };
+        }
+
+        (BigUint {limbs: q}.normalize(), BigUint {limbs: r}.normalize())
+    }
+
+    //Uniformly random, from 0 up to but not including bound.
+    //Draws as many bits as bound needs until it gets one below bound, which takes two tries at
+    //worst on average.
+    pub fn random_below<R: Rng>(rng: &mut R, bound: &BigUint) -> BigUint {
+        if bound.is_zero() {
+            panic!("There's no BigUint below zero.");
+        }
+
+        let bits = bound.bits();
+        let len = bound.limbs.len();
+        let top_bits = bits - (len - 1) * LIMB_BITS as usize;
+
+        loop {
+            let mut limbs: Vec<u32> = (0..len).map(|_| rng.gen()).collect();
+            limbs[len - 1] &= u32::MAX >> (LIMB_BITS as usize - top_bits);
+
+            let n = BigUint {limbs}.normalize();
+            if n < *bound {
+                return n;
+            }
+        }
+    }
+}
+
+//Shift limbs left by s < 32 bits, into len limbs.
+fn shift_left(limbs: &[u32], s: u32, len: usize) -> Vec<u32> {
+    let mut shifted = vec![0u32; len];
+
+    for i in 0..limbs.len() {
+        shifted[i] |= limbs[i] << s;
+        if s > 0 && i + 1 < len {
+            shifted[i + 1] = limbs[i] >> (LIMB_BITS - s);
+        }
+    }
+
+    shifted
+}
+
+impl From<u64> for BigUint {
+    fn from(n: u64) -> BigUint {
+        BigUint {limbs: vec![n as u32, (n >> LIMB_BITS) as u32]}.normalize()
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(n: u128) -> BigUint {
+        let limbs = (0..4).map(|i| (n >> (LIMB_BITS * i)) as u32).collect();
+        BigUint {limbs}.normalize()
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        self.limbs.len().cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let (long, short) = if self.limbs.len() >= other.limbs.len() {
+            (self, other)
+        }
+        else {
+            (other, self)
+        };
+
+        let mut limbs = Vec::with_capacity(long.limbs.len() + 1);
+        let mut carry: u64 = 0;
+
+        for i in 0..long.limbs.len() {
+            let t = long.limbs[i] as u64 + *short.limbs.get(i).unwrap_or(&0) as u64 + carry;
+            limbs.push(t as u32);
+            carry = t >> LIMB_BITS;
+        }
+        limbs.push(carry as u32);
+
+        BigUint {limbs}.normalize()
+    }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+
+        let mut carry: u64 = 0;
+        for i in 0..self.limbs.len() {
+            if i >= other.limbs.len() && carry == 0 {
+                return;
+            }
+
+            let t = self.limbs[i] as u64 + *other.limbs.get(i).unwrap_or(&0) as u64 + carry;
+            self.limbs[i] = t as u32;
+            carry = t >> LIMB_BITS;
+        }
+
+        if carry > 0 {
+            self.limbs.push(carry as u32);
+        }
+    }
+}
+
+//Panics if other is larger, there are no negative BigUints.
+impl Sub for &BigUint {
+    type Output = BigUint;
+
+    fn sub(self, other: &BigUint) -> BigUint {
+        if self < other {
+            panic!("Attempted to subtract a larger BigUint from a smaller one.");
+        }
+
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow: i64 = 0;
+
+        for i in 0..self.limbs.len() {
+            let mut t = self.limbs[i] as i64 - *other.limbs.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = 0;
+            if t < 0 {
+                t += 1 << LIMB_BITS;
+                borrow = 1;
+            }
+            limbs.push(t as u32);
+        }
+
+        BigUint {limbs}.normalize()
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint::zero();
+        }
+
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+
+        for (i, a) in self.limbs.iter().enumerate() {
+            let mut carry: u64 = 0;
+            for (j, b) in other.limbs.iter().enumerate() {
+                let t = *a as u64 * *b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = t as u32;
+                carry = t >> LIMB_BITS;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+
+        BigUint {limbs}.normalize()
+    }
+}
+
+//In decimal.
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        //Peel off 9 decimal digits at a time.
+        let mut chunks = Vec::new();
+        let mut n = self.clone();
+        while !n.is_zero() {
+            let (q, r) = n.div_rem_small(1_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+//From decimal.
+impl FromStr for BigUint {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<BigUint, &'static str> {
+        if s.is_empty() {
+            return Err("Expected a number, found nothing.");
+        }
+
+        let mut n = BigUint::zero();
+        for c in s.chars() {
+            match c.to_digit(10) {
+                Some(digit) => n = n.mul_small(10).add_small(digit),
+                None => return Err("Expected a number made of the digits 0 to 9."),
+            }
+        }
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod big_uint_tests {
+    use super::*;
+
+    //Not random, but all over the place.
+    fn samples() -> Vec<u128> {
+        let mut samples = vec![0, 1, 2, u32::MAX as u128, 1 << 32, u64::MAX as u128, (1 << 64) + 1, u128::MAX];
+        let mut x: u128 = 0x9e37_79b9_7f4a_7c15_f39c_c060_5ced_c834;
+        for i in 0..40 {
+            x = x.wrapping_mul(0x2545_f491_4f6c_dd1d_d6e8_feb8_6659_fd93).wrapping_add(i);
+            samples.push(x >> (i * 3 % 120));
+        }
+        samples
+    }
+
+    #[test]
+    fn random_below_bound() {
+        let mut rng = rand::thread_rng();
+
+        for &bound in samples().iter().filter(|&&bound| bound != 0) {
+            let big_bound = BigUint::from(bound);
+            for _ in 0..20 {
+                assert!(BigUint::random_below(&mut rng, &big_bound) < big_bound);
+            }
+        }
+
+        let mut seen = [false; 3];
+        for _ in 0..100 {
+            let n = BigUint::random_below(&mut rng, &BigUint::from(3u64));
+            seen[n.to_u128().unwrap() as usize] = true;
+        }
+        assert_eq!(seen, [true; 3]);
+    }
+
+    #[test]
+    fn matches_u128() {
+        let samples = samples();
+
+        for &a in &samples {
+            for &b in &samples {
+                let (big_a, big_b) = (BigUint::from(a), BigUint::from(b));
+
+                if let Some(sum) = a.checked_add(b) {
+                    assert_eq!((&big_a + &big_b).to_u128(), Some(sum));
+                }
+                let mut sum = big_a.clone();
+                sum += &big_b;
+                assert_eq!(sum, &big_a + &big_b);
+                if let Some(product) = a.checked_mul(b) {
+                    assert_eq!((&big_a * &big_b).to_u128(), Some(product));
+                }
+                if a >= b {
+                    assert_eq!((&big_a - &big_b).to_u128(), Some(a - b));
+                }
+                if let (Some(quotient), Some(remainder)) = (a.checked_div(b), a.checked_rem(b)) {
+                    let (q, r) = big_a.div_rem(&big_b);
+                    assert_eq!((q.to_u128(), r.to_u128()), (Some(quotient), Some(remainder)));
+                }
+                assert_eq!(big_a.cmp(&big_b), a.cmp(&b));
+            }
+        }
+    }
+
+    #[test]
+    fn division_past_u128() {
+        let samples = samples();
+
+        for &a in &samples {
+            for &b in samples.iter().filter(|&&b| b != 0) {
+                for &c in &samples {
+                    let big_b = BigUint::from(b);
+                    let big_c = BigUint::from(c % b);
+                    let n = &(&BigUint::from(a) * &big_b) + &big_c;
+
+                    assert_eq!(n.div_rem(&big_b), (BigUint::from(a), big_c));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decimal_round_trip() {
+        for &a in &samples() {
+            assert_eq!(BigUint::from(a).to_string(), a.to_string());
+            assert_eq!(a.to_string().parse::<BigUint>(), Ok(BigUint::from(a)));
+        }
+
+        let huge = "123456789012345678901234567890123456789012345678901234567890";
+        assert_eq!(huge.parse::<BigUint>().unwrap().to_string(), huge);
+        assert!("12a".parse::<BigUint>().is_err());
+        assert!("".parse::<BigUint>().is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use rand::Rng;
+
+use crate::sticks::Stick;
+use crate::choose_amongst::Lottery;
+use crate::edit;
+use crate::tree_gen_recursive::{BRANCH_STICKS, LEAF_STICKS};
+
+//Breeding trees, for searching for a tree with a shape in mind, or just to see what comes of it.
+//
+//Like edit, only whole sticks and branches are ever changed, so the BranchIndicators and
+//BranchReturns still match, and leaves stay in leaflets. Nothing's checked against a Config,
+//so a tree can grow in ways its parents' Config never would.
+
+#[derive(Copy, Clone, Debug)]
+enum Mutation {
+    //Going another way.
+    Turn,
+    //Another stick on the branch, or another leaf on the leaflet.
+    Lengthen,
+    //Leaves only, a leaf gone.
+    Drop,
+}
+
+//Every branch and leaf changes rate times out of 100, turned, lengthened or, for leaves, dropped.
+//Whatever hangs off of a leaf stays with it, and goes with it when it's dropped.
+pub fn mutate(rng: &mut rand::rngs::ThreadRng, tree: &[Stick], rate: usize) -> String {
+    let mut output = String::new();
+    let mut i = 0;
+
+    while i < tree.len() {
+        let stick = tree[i];
+        let c = stick.to_char();
+        i += 1;
+        let sticks: &[Stick] = if stick.is_leaf() {
+            &LEAF_STICKS
+        }
+        else if !stick.is_control_char() && !stick.is_decoration() {
+            &BRANCH_STICKS
+        }
+        else {
+            output.push(c);
+            continue;
+        };
+
+        //The decorations hung off of this leaf, if it is one.
+        let decorated_until = i + tree[i..].iter().take_while(|next| next.is_decoration()).count();
+        let decorations: String = tree[i..decorated_until].iter().map(|decoration| decoration.to_char()).collect();
+        i = decorated_until;
+
+        if rng.gen_range(0..100) >= rate {
+            output.push(c);
+            output.push_str(&decorations);
+            continue;
+        }
+
+        let mut lottery: Lottery<Mutation> = Lottery::build(rng);
+        lottery.add(Mutation::Turn);
+        lottery.add(Mutation::Lengthen);
+        if stick.is_leaf() {
+            lottery.add(Mutation::Drop);
+        }
+
+        match lottery.choose() {
+            Mutation::Turn => {
+                let others: Vec<Stick> = sticks.iter().copied().filter(|&other| other != stick).collect();
+                output.push(others[rng.gen_range(0..others.len())].to_char());
+                output.push_str(&decorations);
+            },
+            Mutation::Lengthen => {
+                output.push(c);
+                output.push_str(&decorations);
+                output.push(sticks[rng.gen_range(0..sticks.len())].to_char());
+            },
+            Mutation::Drop => (),
+        }
+    }
+
+    output
+}
+
+//Two children of a and b. One's a with a branch of b in place of one of its own, the other's b
+//with that branch of a in its place. The branches swapped are as deep as each other, so a branch
+//off of the trunk only swaps with another branch off of the trunk.
+//If there's nothing to swap, the children are just like their parents.
+pub fn crossover(rng: &mut rand::rngs::ThreadRng, a: &str, b: &str) -> Result<(String, String), &'static str> {
+    let a_spans = edit::branch_spans(a)?;
+    let b_spans = edit::branch_spans(b)?;
+
+    let mut pairs = Vec::new();
+    for (n, a_span) in a_spans.iter().enumerate() {
+        for (m, b_span) in b_spans.iter().enumerate() {
+            if a_span.depth == b_span.depth {
+                pairs.push((n, m));
+            }
+        }
+    }
+
+    if pairs.is_empty() {
+        return Ok((a.to_string(), b.to_string()));
+    }
+
+    let (n, m) = pairs[rng.gen_range(0..pairs.len())];
+
+    Ok((edit::graft(a, n, b, m)?, edit::graft(b, m, a, n)?))
+}
+
+#[cfg(test)]
+mod breed_tests {
+    use super::*;
+    use crate::sticks::to_sticks;
+    use crate::{Fvtree, FvtreeString, Config};
+
+    #[test]
+    fn mutating() {
+        let mut rng = rand::thread_rng();
+        let tree_string = "||y/o^@l<r|y\\yo>rr|";
+        let tree = to_sticks(tree_string).unwrap();
+
+        assert_eq!(mutate(&mut rng, &tree, 0), tree_string);
+
+        for _ in 0..50 {
+            let mutant = mutate(&mut rng, &tree, 100);
+            assert_ne!(mutant, tree_string);
+            //The control characters are just where they were.
+            let skeleton = |tree_string: &str| -> String {
+                tree_string.chars().filter(|c| "yrol".contains(*c)).collect()
+            };
+            assert_eq!(skeleton(&mutant), skeleton(tree_string));
+            //The fruit's still on its leaf, unless that's been dropped.
+            assert!(mutant.matches('@').count() <= 1);
+            assert!(is_decorated_leaves_only(&mutant), "{}", mutant);
+        }
+    }
+
+    //Whether every decoration in tree_string hangs off of a leaf.
+    fn is_decorated_leaves_only(tree_string: &str) -> bool {
+        let mut hung_off = None;
+        for stick in to_sticks(tree_string).unwrap() {
+            if stick.is_decoration() {
+                if !hung_off.is_some_and(|leaf: Stick| leaf.is_leaf()) {
+                    return false;
+                }
+            }
+            else {
+                hung_off = Some(stick);
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn dropping_decorated_leaves() {
+        let mut rng = rand::thread_rng();
+        let tree_string = "||yo^@>*%l<@r";
+        let tree = to_sticks(tree_string).unwrap();
+
+        for _ in 0..50 {
+            let mutant = mutate(&mut rng, &tree, 100);
+            assert!(is_decorated_leaves_only(&mutant), "{}", mutant);
+            //Whatever hangs off of a leaf stays together, or goes together.
+            let hung: Vec<&str> = mutant.split(|c: char| !"@*%".contains(c)).filter(|hung| !hung.is_empty()).collect();
+            assert!(hung.iter().all(|hung| *hung == "@" || *hung == "*%"), "{}", mutant);
+        }
+    }
+
+    #[test]
+    fn crossing_over() {
+        let mut rng = rand::thread_rng();
+
+        //Only one pair of branches as deep as each other, since there's only one off of either trunk.
+        assert_eq!(crossover(&mut rng, "|y/y|rr|", "||y\\o^r|"), Ok(("|y\\o^r|".to_string(), "||y/y|rr|".to_string())));
+        //Nothing to swap.
+        assert_eq!(crossover(&mut rng, "|y/r|", "||"), Ok(("|y/r|".to_string(), "||".to_string())));
+        assert!(crossover(&mut rng, "|y/", "||").is_err());
+    }
+
+    #[test]
+    fn breeding() {
+        let mut rng = rand::thread_rng();
+        let mut a = FvtreeString::new_recursive_from_config(&Config::new_fruit_tree());
+        let mut b = FvtreeString::new();
+
+        for _ in 0..20 {
+            let (child_a, child_b) = FvtreeString::crossover(&a, &b, &mut rng).unwrap();
+            let branches = |tree: &FvtreeString| tree.tree_string().matches('y').count();
+            assert_eq!(branches(&child_a) + branches(&child_b), branches(&a) + branches(&b));
+
+            a = child_a.mutate(&mut rng, 10);
+            b = FvtreeString::mutate(&child_b, &mut rng, 10);
+            assert!(Fvtree::build(&a).is_ok());
+            assert!(Fvtree::build(&b).is_ok());
+            assert!(is_decorated_leaves_only(&a.tree_string()), "{}", a);
+            assert!(is_decorated_leaves_only(&b.tree_string()), "{}", b);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::str::Chars;
+
+use common::Point;
+
+use crate::sticks::Stick;
+
+//Tree strings that render the same, in every season, brought down to the same tree string.
+//
+//Sticks that change nothing are taken out:
+//- a LeafReturn when the cursor's already at the LeafSpawn, or is about to go back down the
+//  branch anyway.
+//- a branch stick at the end of its branch that lands on a Y or the root. Fvtree::build doesn't
+//  draw those, and with nothing after it on the branch, there's no telling it was there.
+//
+//Then branches off of the same branch point, one right after the other, are put in order, as
+//long as that's render-equivalent. That's when they don't draw on any of the same cells, and
+//they both have an even number of leaves, since seasons go by which leaves come first.
+//
+//Only what's certain to render the same is changed, so two tree strings that render the same
+//won't always come out the same, but most will.
+
+//Cells every season has a Y or the root on, so branch sticks landing there aren't drawn.
+struct Marks {
+    cells: HashSet<(i32, i32)>,
+}
+
+impl Marks {
+    fn new() -> Marks {
+        let mut cells = HashSet::new();
+        cells.insert((0, 0));
+
+        Marks {cells}
+    }
+}
+
+fn is_stick(c: Option<&char>, stick: Stick) -> bool {
+    c == Some(&stick.to_char())
+}
+
+fn is_decoration(c: Option<&char>) -> bool {
+    c.is_some_and(|&c| Stick::try_from(c).is_ok_and(|stick| stick.is_decoration()))
+}
+
+fn remove_no_ops(tree_string: &str) -> Result<String, &'static str> {
+    let chars: Vec<char> = tree_string.chars().collect();
+    let mut keep = vec![true; chars.len()];
+    let mut cursor = Point {x: 0, y: 0};
+    let mut leaf_spawn_point = cursor;
+    let mut branch_points = Vec::new();
+    let mut marks = Marks::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        //Whether the cursor goes straight back down the branch after this, or there's nothing
+        //after it at all. A decoration after the BranchReturn could still tell.
+        let ends_branch = i + 1 == chars.len()
+            || (is_stick(chars.get(i + 1), Stick::BranchReturn) && !is_decoration(chars.get(i + 2)));
+
+        match Stick::try_from(c)? {
+            Stick::BranchIndicator => {
+                marks.cells.insert((cursor.x, cursor.y));
+                branch_points.push(cursor);
+            },
+            Stick::BranchReturn => cursor = branch_points.pop().ok_or("Found a BranchReturn but not a corresponding BranchIndicator.")?,
+            Stick::LeafSpawn => leaf_spawn_point = cursor,
+            Stick::LeafReturn => {
+                cursor = leaf_spawn_point;
+                let already_back = i > 0 && (is_stick(chars.get(i - 1), Stick::LeafSpawn) || is_stick(chars.get(i - 1), Stick::LeafReturn));
+                if already_back || ends_branch {
+                    keep[i] = false;
+                }
+            },
+            stick if stick.is_decoration() => (),
+            stick => {
+                cursor += stick.cursor_move()?;
+                //Leaves are drawn over whatever's there, except in winter.
+                if stick.is_leaf() {
+                    marks.cells.remove(&(cursor.x, cursor.y));
+                }
+                else if marks.cells.contains(&(cursor.x, cursor.y)) && ends_branch {
+                    keep[i] = false;
+                }
+            },
+        }
+    }
+
+    Ok(chars.iter().zip(keep).filter(|(_, keep)| *keep).map(|(c, _)| c).collect())
+}
+
+enum Part {
+    Stick(char),
+    //What's between its BranchIndicator and BranchReturn.
+    Branch(Vec<Part>),
+}
+
+impl Part {
+    fn read(chars: &mut Chars, is_trunk: bool) -> Result<Vec<Part>, &'static str> {
+        let mut parts = Vec::new();
+
+        while let Some(c) = chars.next() {
+            match Stick::try_from(c)? {
+                Stick::BranchIndicator => parts.push(Part::Branch(Part::read(chars, false)?)),
+                Stick::BranchReturn if is_trunk => return Err("Found a BranchReturn but not a corresponding BranchIndicator."),
+                Stick::BranchReturn => return Ok(parts),
+                _ => parts.push(Part::Stick(c)),
+            }
+        }
+
+        if is_trunk {
+            Ok(parts)
+        }
+        else {
+            Err("Found a BranchIndicator but not a corresponding BranchReturn.")
+        }
+    }
+
+    fn to_tree_string(parts: &[Part]) -> String {
+        parts.iter()
+            .map(|part| match part {
+                Part::Stick(c) => c.to_string(),
+                Part::Branch(branch) => format!("{}{}{}", Stick::BranchIndicator.to_char(), Part::to_tree_string(branch), Stick::BranchReturn.to_char()),
+            })
+            .collect()
+    }
+}
+
+//Every cell a branch draws on, from its branch point, which only counts if something other than
+//the branch's own BranchIndicator is drawn there. Then how many leaves it has.
+fn drawn_cells(branch: &str) -> (HashSet<(i32, i32)>, usize) {
+    let start = Point {x: 0, y: 0};
+    let mut cursor = start;
+    let mut leaf_spawn_point = cursor;
+    let mut branch_points = Vec::new();
+    let mut cells = HashSet::new();
+    let mut leaves = 0;
+
+    for (i, c) in branch.chars().enumerate() {
+        match Stick::try_from(c).expect("Branches are made of canonical sticks.") {
+            Stick::BranchIndicator => {
+                if i > 0 {
+                    cells.insert((cursor.x, cursor.y));
+                }
+                branch_points.push(cursor);
+            },
+            Stick::BranchReturn => cursor = branch_points.pop().unwrap_or(start),
+            Stick::LeafSpawn => {
+                leaf_spawn_point = cursor;
+                cells.insert((cursor.x, cursor.y));
+            },
+            Stick::LeafReturn => cursor = leaf_spawn_point,
+            stick if stick.is_decoration() => (),
+            stick => {
+                cursor += stick.cursor_move().expect("Everything else is a branch or a leaf.");
+                cells.insert((cursor.x, cursor.y));
+                if stick.is_leaf() {
+                    leaves += 1;
+                }
+            },
+        }
+    }
+
+    (cells, leaves)
+}
+
+//Whether two branches off of the same branch point, one right after the other, render the same
+//the other way around.
+fn can_swap(a: &str, b: &str) -> bool {
+    let (a_cells, a_leaves) = drawn_cells(a);
+    let (b_cells, b_leaves) = drawn_cells(b);
+
+    a_cells.is_disjoint(&b_cells)
+        && !a_cells.contains(&(0, 0)) && !b_cells.contains(&(0, 0))
+        && a_leaves % 2 == 0 && b_leaves % 2 == 0
+}
+
+fn sort_branches(parts: &mut [Part]) {
+    for part in parts.iter_mut() {
+        if let Part::Branch(branch) = part {
+            sort_branches(branch);
+        }
+    }
+
+    //Bubble sort, since only branches right next to each other can be swapped.
+    loop {
+        let mut swapped = false;
+
+        for i in 1..parts.len() {
+            if let (Part::Branch(_), Part::Branch(_)) = (&parts[i - 1], &parts[i]) {
+                let a = Part::to_tree_string(&parts[i - 1..i]);
+                let b = Part::to_tree_string(&parts[i..=i]);
+                if b < a && can_swap(&a, &b) {
+                    parts.swap(i - 1, i);
+                    swapped = true;
+                }
+            }
+        }
+
+        if !swapped {
+            return;
+        }
+    }
+}
+
+//The tree string with no-op sticks taken out and its branches in order, see above.
+pub fn canonicalize(tree_string: &str) -> Result<String, &'static str> {
+    let mut canonical = tree_string.to_string();
+
+    loop {
+        let mut parts = Part::read(&mut remove_no_ops(&canonical)?.chars(), true)?;
+        //A LeafReturn or decoration right after a branch can still see what was on it,
+        //so branches can't be moved around at all.
+        let sees_into_branches = canonical.chars().zip(canonical.chars().skip(1))
+            .any(|(c, next)| c == Stick::BranchReturn.to_char() && (next == Stick::LeafReturn.to_char() || is_decoration(Some(&next))));
+        if !sees_into_branches {
+            sort_branches(&mut parts);
+        }
+
+        let next = Part::to_tree_string(&parts);
+        if next == canonical {
+            return Ok(canonical);
+        }
+        canonical = next;
+    }
+}
+
+//FNV-1a, which is simple enough to stay the same everywhere and forever, unlike std's hashers.
+pub fn content_hash(tree_string: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    tree_string.bytes().fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+#[cfg(test)]
+mod canonical_tests {
+    use super::*;
+    use crate::{Fvtree, FvtreeString, Config, Season};
+
+    #[test]
+    fn no_op_leaf_returns() {
+        assert_eq!(canonicalize("||y/o^lr|"), Ok("||y/o^r|".to_string()));
+        assert_eq!(canonicalize("||o^ll<l"), Ok("||o^l<".to_string()));
+        assert_eq!(canonicalize("||ol^"), Ok("||o^".to_string()));
+        //Not when it takes the cursor back for more leaves or the trunk.
+        assert_eq!(canonicalize("||o^l<l|"), Ok("||o^l<l|".to_string()));
+    }
+
+    #[test]
+    fn no_op_branch_sticks() {
+        //The last branch goes up onto the Y of the one before it, with nothing after it,
+        //then the bare branch left goes first.
+        assert_eq!(canonicalize("|y|y/rry|r"), Ok("|yry|y/rr".to_string()));
+        //Not if a leaf's been drawn over the Y, or there's more on the branch.
+        assert_eq!(canonicalize("|y|y/rro^ly|r"), Ok("|y|y/rro^ly|r".to_string()));
+        assert_eq!(canonicalize("|y|y/rry||r"), Ok("|y|y/rry||r".to_string()));
+    }
+
+    #[test]
+    fn branch_order() {
+        assert_eq!(canonicalize("||y\\ry/r|"), Ok("||y/ry\\r|".to_string()));
+        //They'd draw over each other.
+        assert_eq!(canonicalize("||y||ry/\\r|"), Ok("||y||ry/\\r|".to_string()));
+        //An odd number of leaves, so the other one's would blossom instead in spring.
+        assert_eq!(canonicalize("||y\\o<ry/o^r|"), Ok("||y\\o<ry/o^r|".to_string()));
+        assert_eq!(canonicalize("||y\\o<l^ry/o^l>r|"), Ok("||y/o^l>ry\\o<l^r|".to_string()));
+    }
+
+    #[test]
+    fn hashing() {
+        assert_eq!(content_hash(""), 0xcbf29ce484222325);
+        assert_eq!(content_hash("||y/r"), content_hash("||y/r"));
+        assert_ne!(content_hash("||y/r"), content_hash("||y\\r"));
+    }
+
+    #[test]
+    fn canonical_form() {
+        let seasons = [Season::Spring, Season::Summer, Season::Autumn, Season::Winter];
+
+        for _ in 0..50 {
+            let fvtree_string = FvtreeString::new_recursive_from_config(&Config::new_fruit_tree()).mutate(&mut rand::thread_rng(), 30);
+            let canonical = fvtree_string.canonicalize().unwrap();
+
+            for season in seasons {
+                assert_eq!(
+                    Fvtree::build_in_season(&canonical, season).unwrap().to_string(),
+                    Fvtree::build_in_season(&fvtree_string, season).unwrap().to_string(),
+                );
+            }
+            assert_eq!(canonical.canonicalize().unwrap(), canonical);
+        }
+
+        let a = FvtreeString::build("v3:||y\\o<l^ry/o^l>lr|").unwrap();
+        let b = FvtreeString::build("v3:||y/o^l>ry\\o<l^r|").unwrap();
+        assert_ne!(a.content_hash(), b.content_hash());
+        assert_eq!(a.canonicalize().unwrap(), b.canonicalize().unwrap());
+        assert_eq!(a.canonicalize().unwrap().content_hash(), b.canonicalize().unwrap().content_hash());
+
+        assert!(FvtreeString::build("v3:||l").unwrap().canonicalize().is_err());
+    }
+}
@@ -9,13 +9,15 @@ use std::fmt::Debug;
 //https://bugs.python.org/msg197540, so we use "Roulette Wheel",
 //i.e. binary search on cumulative sums of the weights.
 #[derive(Debug)]
-pub struct Lottery<'a, T: Copy> {
-    rng: &'a mut rand::rngs::ThreadRng,
+pub struct Lottery<'a, T: Copy, R: Rng = rand::rngs::ThreadRng> {
+    rng: &'a mut R,
     possible_outcomes: Vec<T>,
     //A weight is the ratio of an outcome's chance to be chosen to another outcome's chance to be
     //chosen. E.g. if pumpkin has weight 3 and lemon has weight 1, pumpkin is 3x more likely to be
     //chosen over lemon. An outcome's weight is never 0.
     weights: Vec<usize>,
+    //Outcomes added with a weight of 0, which are only chosen when there's nothing else.
+    last_resorts: Vec<T>,
     //An internal value that is never made known to the user for the sake of simplicity.
     //Denotes whether cumulative_weights can be used for choose_with_bias(), that is,
     //successive calls to choose_with_bias() will use the same cumulative_weights.
@@ -23,12 +25,13 @@ pub struct Lottery<'a, T: Copy> {
     cumulative_weights: Vec<usize>,
 }
 
-impl<T: Copy + Debug> Lottery<'_, T> {
-    pub fn build(rng: &mut rand::rngs::ThreadRng) -> Lottery<T> {
+impl<T: Copy + Debug, R: Rng> Lottery<'_, T, R> {
+    pub fn build(rng: &mut R) -> Lottery<'_, T, R> {
         Lottery {
             rng,
             possible_outcomes: Vec::new(),
             weights: Vec::new(),
+            last_resorts: Vec::new(),
             is_compiled: false,
             cumulative_weights: Vec::new(),
         }
@@ -54,16 +57,18 @@ impl<T: Copy + Debug> Lottery<'_, T> {
         self.weights.resize(self.possible_outcomes.len(), default_bias);
     }
 
+    //A bias of 0 leaves outcome out, unless there's nothing else to choose.
     pub fn add_with_bias(&mut self, outcome: T, bias: usize) {
+        if bias == 0 {
+            self.last_resorts.push(outcome);
+            return;
+        }
+
         self.is_compiled = false;
         if self.is_unbiased() {
             self.gen_default_bias();
         }
 
-        if bias == 0 {
-            panic!("Bias must never be zero.");
-        }
-
         self.possible_outcomes.push(outcome);
         self.weights.push(bias);
 
@@ -103,6 +108,12 @@ impl<T: Copy + Debug> Lottery<'_, T> {
     }
 
     pub fn choose(&mut self) -> T {
+        if self.possible_outcomes.is_empty() && !self.last_resorts.is_empty() {
+            let num = self.rng.gen_range(0..self.last_resorts.len());
+
+            return self.last_resorts[num];
+        }
+
         if self.is_unbiased() {
             return self.choose_without_bias();
         }
@@ -147,4 +158,18 @@ mod choose_amongst_tests {
 
         assert!(true);
     }
+
+    #[test]
+    fn zero_bias() {
+        let mut rng = rand::thread_rng();
+        let mut lot: Lottery<'_, char> = Lottery::build(&mut rng);
+        lot.add_with_bias('a', 0);
+        lot.add_with_bias('b', 1);
+        assert!((0..100).all(|_| lot.choose() == 'b'));
+
+        //Nothing else to choose.
+        let mut lot: Lottery<'_, char> = Lottery::build(&mut rng);
+        lot.add_with_bias('a', 0);
+        assert_eq!(lot.choose(), 'a');
+    }
 }
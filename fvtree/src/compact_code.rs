@@ -0,0 +1,186 @@
+use crate::sticks;
+use crate::sticks::Stick;
+
+//Compact codes are short, URL-safe names for tree strings.
+//
+//The first byte is the language version the sticks were written in, the rest are the sticks
+//packed two to a byte, high nibble first. The first 15 sticks fit in a nibble each, and the
+//16th nibble escapes to the sticks that came later, so those cost two.
+//If there's an odd number of nibbles, a lone escape at the end pads out the last byte.
+//The bytes are then written out in base64url, without padding.
+
+const NIBBLE_STICKS: [char; 15] = ['|', '\\', '/', '^', '<', '>', 'v', 't', 'u', 'b', 'm', 'y', 'r', 'o', 'l'];
+const ESCAPED_STICKS: [char; 3] = ['@', '*', '%'];
+const ESCAPE: u8 = 15;
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn to_nibbles(sticks: &[Stick]) -> Vec<u8> {
+    let mut nibbles = Vec::with_capacity(sticks.len());
+
+    for stick in sticks {
+        let c = stick.to_char();
+        if let Some(i) = NIBBLE_STICKS.iter().position(|&s| s == c) {
+            nibbles.push(i as u8);
+        }
+        //Every canonical stick is one or the other.
+        else if let Some(i) = ESCAPED_STICKS.iter().position(|&s| s == c) {
+            nibbles.push(ESCAPE);
+            nibbles.push(i as u8);
+        }
+    }
+
+    nibbles
+}
+
+fn from_nibbles(nibbles: &[u8]) -> Result<String, &'static str> {
+    let mut tree_string = String::with_capacity(nibbles.len());
+    let mut i = 0;
+
+    while i < nibbles.len() {
+        if nibbles[i] != ESCAPE {
+            tree_string.push(NIBBLE_STICKS[nibbles[i] as usize]);
+        }
+        //The padding at the end.
+        else if i + 1 == nibbles.len() {
+            break;
+        }
+        else {
+            i += 1;
+            match ESCAPED_STICKS.get(nibbles[i] as usize) {
+                Some(&c) => tree_string.push(c),
+                None => return Err("Compact code has an escape to a stick that doesn't exist."),
+            }
+        }
+
+        i += 1;
+    }
+
+    Ok(tree_string)
+}
+
+fn to_base64url(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity((bytes.len() * 4).div_ceil(3));
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        //A chunk of n bytes needs n + 1 characters.
+        for i in 0..=chunk.len() {
+            output.push(BASE64URL[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+
+    output
+}
+
+fn from_base64url(text: &str) -> Result<Vec<u8>, &'static str> {
+    let mut sextets = Vec::with_capacity(text.len());
+    for c in text.bytes() {
+        match BASE64URL.iter().position(|&b| b == c) {
+            Some(i) => sextets.push(i as u32),
+            None => return Err("Compact code contains a character that isn't base64url."),
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(sextets.len() * 3 / 4);
+    for chunk in sextets.chunks(4) {
+        if chunk.len() == 1 {
+            return Err("Compact code has the wrong length.");
+        }
+
+        let mut n = 0;
+        for (i, sextet) in chunk.iter().enumerate() {
+            n |= sextet << (18 - 6 * i);
+        }
+
+        for i in 0..chunk.len() - 1 {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    Ok(bytes)
+}
+
+pub fn encode(sticks: &[Stick]) -> String {
+    let mut nibbles = to_nibbles(sticks);
+    if !nibbles.len().is_multiple_of(2) {
+        nibbles.push(ESCAPE);
+    }
+
+    let mut bytes = Vec::with_capacity(1 + nibbles.len() / 2);
+    bytes.push(sticks::CURRENT_LANGUAGE_VERSION as u8);
+    for pair in nibbles.chunks(2) {
+        bytes.push(pair[0] << 4 | pair[1]);
+    }
+
+    to_base64url(&bytes)
+}
+
+//Returns a current version tree string.
+pub fn decode(code: &str) -> Result<String, &'static str> {
+    let bytes = from_base64url(code)?;
+
+    let version = match bytes.first() {
+        Some(&version) => version as u32,
+        None => return Err("Compact code is empty."),
+    };
+
+    let mut nibbles = Vec::with_capacity((bytes.len() - 1) * 2);
+    for byte in &bytes[1..] {
+        nibbles.push(byte >> 4);
+        nibbles.push(byte & 0xf);
+    }
+
+    let tree_string = from_nibbles(&nibbles)?;
+
+    sticks::migrate(&tree_string, version)
+}
+
+#[cfg(test)]
+mod compact_code_tests {
+    use super::*;
+    use crate::{FvtreeString, Config};
+
+    #[test]
+    fn base64url_round_trip() {
+        for len in 0..10 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37 + 200) as u8).collect();
+            assert_eq!(from_base64url(&to_base64url(&bytes)), Ok(bytes));
+        }
+        assert_eq!(to_base64url(b"fvtree"), "ZnZ0cmVl");
+    }
+
+    #[test]
+    fn code_round_trip() {
+        for tree_string in ["", "|", "||y\\o^@l*r", "//|\\y\\|\\r//|", "%"].iter() {
+            let code = encode(&sticks::to_sticks(tree_string).unwrap());
+            assert_eq!(decode(&code), Ok(tree_string.to_string()));
+        }
+    }
+
+    #[test]
+    fn code_is_url_safe() {
+        let code = encode(&sticks::to_sticks("|\\/^<>vtubmyrol@*%").unwrap());
+        assert!(code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+
+    #[test]
+    fn bad_codes() {
+        assert!(decode("").is_err());
+        assert!(decode("A").is_err());
+        assert!(decode("a+b").is_err());
+        //v200.
+        assert!(decode(&to_base64url(&[200, 0x01])).is_err());
+        //An escape to a stick that doesn't exist.
+        assert!(decode(&to_base64url(&[3, 0xf9])).is_err());
+    }
+
+    #[test]
+    fn fvtree_string_round_trip() {
+        let fvtree_string = FvtreeString::new_recursive_from_config(&Config::new_fruit_tree());
+        let read_back = FvtreeString::from_compact_code(&fvtree_string.to_compact_code()).unwrap();
+        assert_eq!(fvtree_string.tree_string(), read_back.tree_string());
+    }
+}
@@ -1,3 +1,11 @@
+use std::collections::HashSet;
+
+use common::Point;
+
+use crate::sticks::Stick;
+use crate::silhouette::{Silhouette, SilhouetteShape};
+
+#[derive(Clone)]
 pub struct TreeConfig {
     pub min_trunk_height: usize,
     pub max_trunk_height: usize,
@@ -8,6 +16,25 @@ pub struct TreeConfig {
     pub min_sticks: usize,
     pub max_sticks: usize,
     pub dead_tree: bool,
+    //Keep track of where sticks land, and only put one down where nothing else is,
+    //unless there's nowhere else for it to go.
+    pub avoid_collisions: bool,
+    //Keep the trunk straight, and mirror every branch off of it on the other side.
+    pub symmetric: bool,
+    //Keep sticks inside this outline, unless there's nowhere else for them to go.
+    pub silhouette: Option<Silhouette>,
+    //The most cells wide and tall the rendered Fvtree can be, root and all, which is 3x2 on its own.
+    //Unlike everything else, these always hold: when nothing fits, the trunk, branch or leaflet
+    //stops, even short of its minimums.
+    pub max_width: Option<usize>,
+    pub max_height: Option<usize>,
+    //How likely each choice the trunk makes is, next to the others, like Lottery::add_with_bias.
+    //A weight of 0 rules a choice out, unless there's nothing else to choose.
+    //Weights only change which trees are likely, not which trees there are.
+    //Up, up left then up right.
+    pub stick_weights: [usize; 3],
+    pub branch_weight: usize,
+    pub stop_weight: usize,
 }
 
 impl TreeConfig {
@@ -20,6 +47,14 @@ impl TreeConfig {
             min_sticks: 20,
             max_sticks: 1000,
             dead_tree: false,
+            avoid_collisions: false,
+            symmetric: false,
+            silhouette: None,
+            max_width: None,
+            max_height: None,
+            stick_weights: [1, 1, 1],
+            branch_weight: 1,
+            stop_weight: 1,
         }
     }
 
@@ -50,6 +85,13 @@ impl TreeConfig {
     */
 }
 
+impl Default for TreeConfig {
+    fn default() -> TreeConfig {
+        TreeConfig::new()
+    }
+}
+
+#[derive(Clone)]
 pub struct BranchConfig {
     pub min_sticks: usize,
     pub max_sticks: usize,
@@ -59,6 +101,22 @@ pub struct BranchConfig {
     pub max_leaves_in_leaflet: usize,
     pub min_leaflets: usize,
     pub max_leaflets: usize,
+    //Chances, out of 100, that a leaf has a decoration hung off of it.
+    //If they add up to 100 or more, every leaf gets one.
+    pub fruit_chance: usize,
+    pub flower_chance: usize,
+    pub blossom_chance: usize,
+    //The same as TreeConfig's weights, for branches. stop_weight goes for dead branches too.
+    pub stick_weights: [usize; 3],
+    pub branch_weight: usize,
+    pub stop_weight: usize,
+    //Up, left, right, down, up left, up right, down left then down right.
+    pub leaf_weights: [usize; 8],
+    //depth_overrides[0] is for the branches off of the trunk, depth_overrides[1] for the branches
+    //off of those, and so on. Branches deeper than the last one go by the last one.
+    pub depth_overrides: Vec<DepthOverride>,
+    //Branches this deep don't branch any further. The trunk always can.
+    pub max_branch_depth: Option<usize>,
 }
 
 impl BranchConfig {
@@ -72,10 +130,89 @@ impl BranchConfig {
             max_leaves_in_leaflet: 3,
             min_leaflets: 4,
             max_leaflets: 5,
+            fruit_chance: 0,
+            flower_chance: 0,
+            blossom_chance: 0,
+            stick_weights: [1, 1, 1],
+            branch_weight: 1,
+            stop_weight: 1,
+            leaf_weights: [1; 8],
+            depth_overrides: Vec::new(),
+            max_branch_depth: None,
+        }
+    }
+
+    //What this comes to for branches depth deep, the trunk's own being 1 deep.
+    pub fn at_depth(&self, depth: usize) -> DepthConfig {
+        let over = self.depth_overrides
+            .get(depth.max(1) - 1)
+            .or(self.depth_overrides.last())
+            .copied()
+            .unwrap_or_default();
+
+        DepthConfig {
+            min_sticks: over.min_sticks.unwrap_or(self.min_sticks),
+            max_sticks: over.max_sticks.unwrap_or(self.max_sticks),
+            branch_weight: over.branch_weight.unwrap_or(self.branch_weight),
+            min_leaflets: over.min_leaflets.unwrap_or(self.min_leaflets),
+            max_leaflets: over.max_leaflets.unwrap_or(self.max_leaflets),
+            can_branch: self.max_branch_depth.is_none_or(|max_branch_depth| depth < max_branch_depth),
         }
     }
+
+    //Sets depth_overrides so branches go from how they are now, off of the trunk,
+    //to twigs depth deep, a little at a time. Anything twigs leaves as None stays as it is.
+    pub fn taper(&mut self, twigs: DepthOverride, depth: usize) {
+        let lerp = |from: usize, to: Option<usize>, i: usize| {
+            to.map(|to| {
+                if depth <= 1 {
+                    return to;
+                }
+                ((from * (depth - 1 - i) + to * i) as f64 / (depth - 1) as f64).round() as usize
+            })
+        };
+
+        self.depth_overrides = (0..depth.max(1))
+            .map(|i| DepthOverride {
+                min_sticks: lerp(self.min_sticks, twigs.min_sticks, i),
+                max_sticks: lerp(self.max_sticks, twigs.max_sticks, i),
+                branch_weight: lerp(self.branch_weight, twigs.branch_weight, i),
+                min_leaflets: lerp(self.min_leaflets, twigs.min_leaflets, i),
+                max_leaflets: lerp(self.max_leaflets, twigs.max_leaflets, i),
+            })
+            .collect();
+    }
+}
+
+//Stands in for some of BranchConfig at one depth. Anything left as None is the same as BranchConfig.
+//branch_weight is the branching chance, next to the sticks and stopping.
+#[derive(Copy, Clone, Default)]
+pub struct DepthOverride {
+    pub min_sticks: Option<usize>,
+    pub max_sticks: Option<usize>,
+    pub branch_weight: Option<usize>,
+    pub min_leaflets: Option<usize>,
+    pub max_leaflets: Option<usize>,
 }
 
+//BranchConfig at one depth, with its DepthOverride and max_branch_depth taken into account.
+#[derive(Copy, Clone)]
+pub struct DepthConfig {
+    pub min_sticks: usize,
+    pub max_sticks: usize,
+    pub branch_weight: usize,
+    pub min_leaflets: usize,
+    pub max_leaflets: usize,
+    pub can_branch: bool,
+}
+
+impl Default for BranchConfig {
+    fn default() -> BranchConfig {
+        BranchConfig::new()
+    }
+}
+
+#[derive(Clone)]
 pub struct Config {
     pub t: TreeConfig,
     pub b: BranchConfig,
@@ -88,13 +225,197 @@ impl Config {
             b: BranchConfig::new(),
         }
     }
+
+    //Seasonal variants of the default tree.
+
+    //Spring, covered in blossoms.
+    pub fn new_blossoming_tree() -> Config {
+        let mut conf = Config::new();
+        conf.b.blossom_chance = 30;
+        conf
+    }
+
+    //Summer, flowering.
+    pub fn new_flowering_tree() -> Config {
+        let mut conf = Config::new();
+        conf.b.flower_chance = 20;
+        conf
+    }
+
+    //Autumn, ready for picking.
+    pub fn new_fruit_tree() -> Config {
+        let mut conf = Config::new();
+        conf.b.fruit_chance = 15;
+        conf
+    }
 }
 
+//Every species Config::new_species knows, and what it looks like.
+pub const SPECIES: &[(&str, &str)] = &[
+    ("oak", "Broad and round, the odd acorn."),
+    ("pine", "Straight up the middle, a cone of short branches."),
+    ("willow", "Long branches spreading out, leaves hanging down."),
+    ("birch", "Tall and narrow, light on leaves."),
+    ("bonsai", "Small enough for a windowsill, in blossom."),
+    ("dead snag", "Bare branches, some broken off."),
+    ("sapling", "Only just planted."),
+];
+
+impl Config {
+    //Species, the look of a whole kind of tree, rather than a season.
+    //Every leaf looks the same, so leaves are down to which way they grow, along with the
+    //silhouette, and what hangs off of them to the decorations.
+    //There's no picking a glyph for a species' leaves: a tree string doesn't say which species
+    //grew it, and it has to look the same however it's read back, with -i, -k or an index.
+
+    pub fn new_oak() -> Config {
+        let mut conf = Config::new();
+        conf.t.min_sticks = 40;
+        conf.t.avoid_collisions = true;
+        conf.t.silhouette = Some(Silhouette {shape: SilhouetteShape::Round, width: 31, height: 18});
+        conf.b.max_sticks = 5;
+        conf.b.max_leaflets = 6;
+        conf.b.fruit_chance = 5;
+        //Spreading out sideways.
+        conf.b.stick_weights = [1, 2, 2];
+        conf.b.leaf_weights = [2, 3, 3, 1, 2, 2, 1, 1];
+        conf
+    }
+
+    pub fn new_pine() -> Config {
+        let mut conf = Config::new();
+        conf.t.min_trunk_height = 6;
+        conf.t.min_height_before_trunk_can_branch = 2;
+        conf.t.height_when_trunk_forced_to_branch = 3;
+        conf.t.min_sticks = 30;
+        conf.t.symmetric = true;
+        conf.t.silhouette = Some(Silhouette {shape: SilhouetteShape::Conical, width: 19, height: 24});
+        conf.b.min_sticks = 1;
+        conf.b.max_sticks = 3;
+        conf.b.min_sticks_before_branch = 3;
+        conf.b.min_leaves_in_leaflet = 1;
+        conf.b.max_leaves_in_leaflet = 2;
+        conf.b.min_leaflets = 2;
+        conf.b.max_leaflets = 3;
+        //Needles pointing up and out.
+        conf.b.stick_weights = [1, 3, 3];
+        conf.b.leaf_weights = [3, 1, 1, 0, 3, 3, 0, 0];
+        conf
+    }
+
+    pub fn new_willow() -> Config {
+        let mut conf = Config::new();
+        conf.t.min_trunk_height = 4;
+        conf.t.min_sticks = 40;
+        conf.t.avoid_collisions = true;
+        conf.t.silhouette = Some(Silhouette::new(SilhouetteShape::Weeping));
+        conf.b.min_sticks = 3;
+        conf.b.max_sticks = 6;
+        //Hanging down.
+        conf.b.leaf_weights = [0, 1, 1, 4, 0, 0, 3, 3];
+        conf
+    }
+
+    pub fn new_birch() -> Config {
+        let mut conf = Config::new();
+        conf.t.min_trunk_height = 8;
+        conf.t.min_height_before_trunk_can_branch = 4;
+        conf.t.height_when_trunk_forced_to_branch = 6;
+        conf.t.silhouette = Some(Silhouette {shape: SilhouetteShape::Columnar, width: 9, height: 22});
+        conf.b.max_sticks = 3;
+        conf.b.min_leaves_in_leaflet = 1;
+        conf.b.max_leaves_in_leaflet = 2;
+        conf.b.min_leaflets = 2;
+        conf.b.max_leaflets = 3;
+        //Reaching up.
+        conf.t.stick_weights = [3, 1, 1];
+        conf.b.stick_weights = [3, 1, 1];
+        conf.b.leaf_weights = [3, 1, 1, 1, 2, 2, 1, 1];
+        conf
+    }
+
+    pub fn new_bonsai() -> Config {
+        let mut conf = Config::new();
+        conf.t.min_trunk_height = 1;
+        conf.t.height_when_trunk_forced_to_branch = 2;
+        conf.t.min_sticks = 8;
+        conf.t.avoid_collisions = true;
+        conf.t.max_width = Some(15);
+        conf.t.max_height = Some(8);
+        conf.b.min_sticks = 1;
+        conf.b.max_sticks = 2;
+        conf.b.min_sticks_before_branch = 1;
+        conf.b.min_leaflets = 2;
+        conf.b.max_leaflets = 3;
+        conf.b.blossom_chance = 20;
+        //Flat pads of leaves.
+        conf.b.leaf_weights = [1, 3, 3, 0, 1, 1, 0, 0];
+        conf
+    }
+
+    pub fn new_dead_snag() -> Config {
+        let mut conf = Config::new();
+        conf.t.min_trunk_height = 5;
+        conf.t.min_sticks = 12;
+        conf.t.dead_tree = true;
+        conf.b.min_sticks = 1;
+        conf.b.max_sticks = 3;
+        conf.b.allow_dead_branches = true;
+        //Broken off more often than not.
+        conf.b.stop_weight = 2;
+        conf
+    }
+
+    pub fn new_sapling() -> Config {
+        let mut conf = Config::new();
+        conf.t.min_trunk_height = 2;
+        conf.t.max_trunk_height = 4;
+        conf.t.min_height_before_trunk_can_branch = 1;
+        conf.t.height_when_trunk_forced_to_branch = 2;
+        conf.t.min_sticks = 3;
+        conf.b.min_sticks = 1;
+        conf.b.max_sticks = 2;
+        conf.b.min_sticks_before_branch = 2;
+        conf.b.min_leaves_in_leaflet = 1;
+        conf.b.max_leaves_in_leaflet = 2;
+        conf.b.min_leaflets = 1;
+        conf.b.max_leaflets = 2;
+        conf
+    }
+
+    //One of SPECIES by name, or None if there's no such species.
+    //Names can have a - for a space, so they don't need quoting on the command line.
+    pub fn new_species(name: &str) -> Option<Config> {
+        match name {
+            "oak" => Some(Config::new_oak()),
+            "pine" => Some(Config::new_pine()),
+            "willow" => Some(Config::new_willow()),
+            "birch" => Some(Config::new_birch()),
+            "bonsai" => Some(Config::new_bonsai()),
+            "dead snag" | "dead-snag" => Some(Config::new_dead_snag()),
+            "sapling" => Some(Config::new_sapling()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+#[derive(Clone)]
 pub struct TreeStats {
     pub height: usize,
     pub num_sticks: usize,
     pub branch_depth: usize,
     pub has_branched: bool,
+    //Every cell something's been put in, the same way Fvtree::build will put it there.
+    pub occupied: HashSet<(i32, i32)>,
+    //The bottom left and top right corners of the box around occupied.
+    pub min_corner: Point,
+    pub max_corner: Point,
 }
 
 impl TreeStats {
@@ -104,8 +425,29 @@ impl TreeStats {
             num_sticks: 0,
             branch_depth: 0,
             has_branched: false,
+            //The root, and the /|\ underneath it.
+            occupied: [(0, 0), (-1, -1), (0, -1), (1, -1)].iter().copied().collect(),
+            min_corner: Point {x: -1, y: -1},
+            max_corner: Point {x: 1, y: 0},
         }
     }
+
+    pub fn occupy(&mut self, (x, y): (i32, i32)) {
+        self.occupied.insert((x, y));
+        self.min_corner = Point {x: self.min_corner.x.min(x), y: self.min_corner.y.min(y)};
+        self.max_corner = Point {x: self.max_corner.x.max(x), y: self.max_corner.y.max(y)};
+    }
+
+    //How wide and tall the tree would be with something at every one of points too.
+    pub fn size_with(&self, points: &[Point]) -> (usize, usize) {
+        let (mut min, mut max) = (self.min_corner, self.max_corner);
+        for point in points {
+            min = Point {x: min.x.min(point.x), y: min.y.min(point.y)};
+            max = Point {x: max.x.max(point.x), y: max.y.max(point.y)};
+        }
+
+        ((max.x - min.x + 1) as usize, (max.y - min.y + 1) as usize)
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -116,6 +458,9 @@ pub struct BranchStats {
     pub is_leaf_state: bool,
     pub num_leaves_in_leaflet: usize,
     pub num_leaflets: usize,
+    //Where the next stick goes from, which comes back here when the branch returns.
+    pub cursor: Point,
+    pub leaf_spawn_point: Point,
 }
 
 impl BranchStats {
@@ -127,6 +472,8 @@ impl BranchStats {
             is_leaf_state: false,
             num_leaves_in_leaflet: 0,
             num_leaflets: 0,
+            cursor: Point {x: 0, y: 0},
+            leaf_spawn_point: Point {x: 0, y: 0},
         }
     }
 }
@@ -158,9 +505,42 @@ impl Stats {
         self.t.branch_depth -= 1;
     }
 
+    //Where stick would land.
+    pub fn landing(&self, stick: Stick) -> Point {
+        self.b.cursor + stick.cursor_move().expect("Only branches and leaves land anywhere.")
+    }
+
+    pub fn is_free(&self, stick: Stick) -> bool {
+        let landing = self.landing(stick);
+        !self.t.occupied.contains(&(landing.x, landing.y))
+    }
+
+    pub fn put_down(&mut self, stick: Stick) {
+        self.b.cursor = self.landing(stick);
+        self.t.occupy((self.b.cursor.x, self.b.cursor.y));
+    }
+
     pub fn add_one_leaf(&mut self) {
         self.add_one_stick();
         self.b.num_leaves += 1;
         self.b.num_leaves_in_leaflet += 1;
     }
 }
+
+#[cfg(test)]
+mod confstats_tests {
+    use super::*;
+    use crate::{Fvtree, FvtreeString};
+
+    #[test]
+    fn species_grow() {
+        for (name, _) in SPECIES {
+            for _ in 0..10 {
+                let fvtree_string = FvtreeString::generate_species(name).unwrap();
+                assert!(Fvtree::build(&fvtree_string).is_ok(), "{}: {}", name, fvtree_string);
+            }
+        }
+        assert!(FvtreeString::generate_species("dead-snag").is_ok());
+        assert!(FvtreeString::generate_species("baobab").is_err());
+    }
+}
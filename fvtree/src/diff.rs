@@ -0,0 +1,274 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::str::Chars;
+
+use common::Point;
+
+use crate::sticks::Stick;
+
+//How cells that differ are marked in visual_diff.
+const ONLY_IN_A_C: char = '-';
+const ONLY_IN_B_C: char = '+';
+const CHANGED_C: char = '~';
+
+//Comparing two trees, branch by branch and leaflet by leaflet.
+//
+//A branch is the same branch in both trees if it starts in the same place and its sticks go the
+//same way, in which case what's on it is compared as well. Otherwise, it's been taken out of one
+//and put into the other, along with everything on it. A leaflet is the same if it's in the same
+//place with the same leaves. The trunk's there in both, and only what's on it is compared.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Change {
+    Added,
+    Removed,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Subtree {
+    Branch,
+    Leaflet,
+}
+
+//A branch or leaflet that's in one tree but not the other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    pub change: Change,
+    pub subtree: Subtree,
+    //Where it starts, on the canvas the tree's built on.
+    pub at: Point,
+    //The branch with everything on it, or the leaflet, from its BranchIndicator or LeafSpawn on.
+    pub tree_string: String,
+}
+
+//A branch, or the trunk, split up into its own sticks and what's on it.
+struct Branch {
+    //Its branch sticks, which say which way it goes.
+    sticks: String,
+    branches: Vec<(Point, Branch)>,
+    leaflets: Vec<(Point, String)>,
+    tree_string: String,
+}
+
+impl Branch {
+    //Reads a branch from just after its BranchIndicator through its BranchReturn,
+    //or the trunk through the end of the tree string.
+    fn read(chars: &mut Chars, mut cursor: Point, is_trunk: bool) -> Result<Branch, &'static str> {
+        let mut branch = Branch {sticks: String::new(), branches: Vec::new(), leaflets: Vec::new(), tree_string: String::new()};
+        let mut leaf_spawn_point = None;
+
+        while let Some(c) = chars.next() {
+            let stick = Stick::try_from(c)?;
+
+            match stick {
+                Stick::BranchIndicator => {
+                    leaf_spawn_point = None;
+                    let mut sub_branch = Branch::read(chars, cursor, false)?;
+                    sub_branch.tree_string.insert(0, c);
+                    branch.tree_string.push_str(&sub_branch.tree_string);
+                    branch.branches.push((cursor, sub_branch));
+                    continue;
+                },
+                Stick::BranchReturn if is_trunk => return Err("Found a BranchReturn but not a corresponding BranchIndicator."),
+                Stick::BranchReturn => {
+                    branch.tree_string.push(c);
+                    return Ok(branch);
+                },
+                Stick::LeafSpawn => {
+                    leaf_spawn_point = Some(cursor);
+                    branch.leaflets.push((cursor, c.to_string()));
+                },
+                Stick::LeafReturn => {
+                    cursor = leaf_spawn_point.ok_or("Found a LeafReturn but not a corresponding LeafSpawn.")?;
+                    branch.leaflets.last_mut().expect("There's a leaflet after a LeafSpawn.").1.push(c);
+                },
+                stick if stick.is_decoration() => {
+                    if leaf_spawn_point.is_none() {
+                        return Err("Found a decoration but not a corresponding LeafSpawn.");
+                    }
+                    branch.leaflets.last_mut().expect("There's a leaflet after a LeafSpawn.").1.push(c);
+                },
+                stick => {
+                    cursor += stick.cursor_move()?;
+                    match leaf_spawn_point {
+                        Some(_) if stick.is_leaf() => branch.leaflets.last_mut().expect("There's a leaflet after a LeafSpawn.").1.push(c),
+                        _ => {
+                            leaf_spawn_point = None;
+                            branch.sticks.push(c);
+                        },
+                    }
+                },
+            }
+
+            branch.tree_string.push(c);
+        }
+
+        if is_trunk {
+            Ok(branch)
+        }
+        else {
+            Err("Found a BranchIndicator but not a corresponding BranchReturn.")
+        }
+    }
+}
+
+//Pairs up the same things in a and b, whatever's left over has been taken out or put in.
+//Returns what's in a but not b, what's in b but not a, and the pairs.
+fn pair_up<'a, T>(a: &'a [T], b: &'a [T], same: impl Fn(&T, &T) -> bool) -> (Vec<&'a T>, Vec<&'a T>, Vec<(&'a T, &'a T)>) {
+    let mut paired = vec![false; b.len()];
+    let mut only_in_a = Vec::new();
+    let mut pairs = Vec::new();
+
+    for x in a.iter() {
+        match (0..b.len()).find(|&i| !paired[i] && same(x, &b[i])) {
+            Some(i) => {
+                paired[i] = true;
+                pairs.push((x, &b[i]));
+            },
+            None => only_in_a.push(x),
+        }
+    }
+    let only_in_b = b.iter().enumerate().filter(|(i, _)| !paired[*i]).map(|(_, y)| y).collect();
+
+    (only_in_a, only_in_b, pairs)
+}
+
+fn diff_branches(a: &Branch, b: &Branch, differences: &mut Vec<Difference>) {
+    let (removed, added, _) = pair_up(&a.leaflets, &b.leaflets, |x, y| x == y);
+    for (change, leaflets) in [(Change::Removed, removed), (Change::Added, added)] {
+        for (at, leaflet) in leaflets {
+            differences.push(Difference {change, subtree: Subtree::Leaflet, at: *at, tree_string: leaflet.to_string()});
+        }
+    }
+
+    let (removed, added, pairs) = pair_up(&a.branches, &b.branches, |x, y| x.0 == y.0 && x.1.sticks == y.1.sticks);
+    for (change, branches) in [(Change::Removed, removed), (Change::Added, added)] {
+        for (at, branch) in branches {
+            differences.push(Difference {change, subtree: Subtree::Branch, at: *at, tree_string: branch.tree_string.to_string()});
+        }
+    }
+    for ((_, a_branch), (_, b_branch)) in pairs {
+        diff_branches(a_branch, b_branch, differences);
+    }
+}
+
+//What's been taken out of a and put into b to get from one to the other.
+pub fn diff(a: &str, b: &str) -> Result<Vec<Difference>, &'static str> {
+    let root = Point {x: 0, y: 0};
+    let a = Branch::read(&mut a.chars(), root, true)?;
+    let b = Branch::read(&mut b.chars(), root, true)?;
+    let mut differences = Vec::new();
+
+    diff_branches(&a, &b, &mut differences);
+
+    Ok(differences)
+}
+
+//How much two rendered trees look alike, from 0 for nothing alike to 1 for exactly alike.
+//It's how many cells are the same in both, out of every cell that's filled in either.
+pub fn similarity(a: &text_canvas::Canvas, b: &text_canvas::Canvas) -> f64 {
+    let cells = |canvas: &text_canvas::Canvas| -> HashSet<(i32, i32, char)> {
+        canvas.cells().into_iter().map(|(point, c)| (point.x, point.y, c)).collect()
+    };
+    let a = cells(a);
+    let b = cells(b);
+    let either = a.union(&b).count();
+
+    if either == 0 {
+        return 1.0;
+    }
+
+    a.intersection(&b).count() as f64 / either as f64
+}
+
+//Both rendered trees on one canvas. What's the same in both is drawn as it is, and the rest is
+//marked, - where only a has something, + where only b does, and ~ where they've both got
+//something different.
+pub fn visual_diff(a: &text_canvas::Canvas, b: &text_canvas::Canvas) -> text_canvas::Canvas {
+    let mut canvas = text_canvas::Canvas::new();
+    let a_cells: HashMap<(i32, i32), char> = a.cells().into_iter().map(|(point, c)| ((point.x, point.y), c)).collect();
+    let b_cells: HashMap<(i32, i32), char> = b.cells().into_iter().map(|(point, c)| ((point.x, point.y), c)).collect();
+
+    for (&(x, y), &c) in a_cells.iter() {
+        let mark = match b_cells.get(&(x, y)) {
+            Some(&other) if other == c => c,
+            Some(_) => CHANGED_C,
+            None => ONLY_IN_A_C,
+        };
+        canvas.put(Point {x, y}, mark);
+    }
+    for (&(x, y), _) in b_cells.iter().filter(|(point, _)| !a_cells.contains_key(point)) {
+        canvas.put(Point {x, y}, ONLY_IN_B_C);
+    }
+
+    canvas
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::*;
+    use crate::{Fvtree, FvtreeString};
+
+    #[test]
+    fn same_tree() {
+        assert_eq!(diff("||y/o^l<r|y\\yo>rr|", "||y/o^l<r|y\\yo>rr|"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn differences() {
+        let differences = diff("||y/o^l<r|y\\yo>rr|", "||y/o^l>r|y\\y|rr|y|r").unwrap();
+
+        assert_eq!(differences, vec![
+            //The trunk's grown, and put out another branch.
+            Difference {change: Change::Added, subtree: Subtree::Branch, at: Point {x: 0, y: 4}, tree_string: "y|r".to_string()},
+            //The leaflet's changed, but the branch it's on is the same.
+            Difference {change: Change::Removed, subtree: Subtree::Leaflet, at: Point {x: 1, y: 3}, tree_string: "o^l<".to_string()},
+            Difference {change: Change::Added, subtree: Subtree::Leaflet, at: Point {x: 1, y: 3}, tree_string: "o^l>".to_string()},
+            //Down on the branch that's on the other branch.
+            Difference {change: Change::Removed, subtree: Subtree::Branch, at: Point {x: -1, y: 4}, tree_string: "yo>r".to_string()},
+            Difference {change: Change::Added, subtree: Subtree::Branch, at: Point {x: -1, y: 4}, tree_string: "y|r".to_string()},
+        ]);
+    }
+
+    #[test]
+    fn visual_differences() {
+        let mut a = text_canvas::Canvas::new();
+        a.put(Point {x: 0, y: 0}, '|');
+        a.put(Point {x: 0, y: 1}, '|');
+        a.put(Point {x: -1, y: 2}, '\\');
+        let mut b = text_canvas::Canvas::new();
+        b.put(Point {x: 0, y: 0}, '|');
+        b.put(Point {x: 0, y: 1}, '/');
+        b.put(Point {x: 1, y: 2}, '/');
+
+        assert_eq!(format!("{}", visual_diff(&a, &b)), "- +\n ~\n |\n");
+        assert_eq!(similarity(&a, &b), 1.0 / 5.0);
+        assert_eq!(similarity(&a, &a), 1.0);
+        assert_eq!(similarity(&text_canvas::Canvas::new(), &text_canvas::Canvas::new()), 1.0);
+    }
+
+    #[test]
+    fn invalid_trees() {
+        assert!(diff("||y|", "||").is_err());
+        assert!(diff("||", "|r|").is_err());
+        assert!(diff("||", "|l").is_err());
+    }
+
+    #[test]
+    fn diffing() {
+        let fvtree_string = FvtreeString::build("v3:||y/o^l<r|y\\yo>rr|").unwrap();
+        assert_eq!(fvtree_string.diff(&fvtree_string), Ok(Vec::new()));
+        assert_eq!(fvtree_string.similarity(&fvtree_string), Ok(1.0));
+
+        let pruned = fvtree_string.prune(0).unwrap();
+        assert_eq!(fvtree_string.diff(&pruned), Ok(vec![
+            Difference {change: Change::Removed, subtree: Subtree::Branch, at: Point {x: 0, y: 2}, tree_string: "y/o^l<r".to_string()},
+        ]));
+        let similarity = fvtree_string.similarity(&pruned).unwrap();
+        assert!(similarity > 0.0 && similarity < 1.0);
+
+        let visual_diff = Fvtree::diff(&fvtree_string, &pruned).unwrap();
+        assert!(visual_diff.to_string().contains('-'));
+        assert!(!visual_diff.to_string().contains('+'));
+    }
+}
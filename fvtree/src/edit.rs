@@ -0,0 +1,206 @@
+use std::convert::TryFrom;
+
+use common::Point;
+
+use crate::sticks::{Stick, StickCanonical};
+use crate::sticks::{BranchIndicator, BranchReturn};
+
+//Cutting tree strings up and putting them back together.
+//
+//Branches are numbered from 0 in the order their BranchIndicators come in the tree string, and a
+//branch is everything from its BranchIndicator to its BranchReturn, so whatever branches off of
+//it goes with it. Every edit takes out or puts in whole branches, leaflets or sticks, so the
+//BranchIndicators and BranchReturns still match, and leaves and decorations stay in leaflets.
+
+//Where a branch starts and ends in a tree string, its BranchIndicator and BranchReturn.
+pub struct BranchSpan {
+    pub start: usize,
+    pub end: usize,
+    //How many branches it's on, 0 for branches off of the trunk.
+    pub depth: usize,
+}
+
+//Every branch, in the order they start.
+pub fn branch_spans(tree_string: &str) -> Result<Vec<BranchSpan>, &'static str> {
+    let mut spans = Vec::new();
+    let mut open = Vec::new();
+
+    for (i, c) in tree_string.char_indices() {
+        if c == BranchIndicator::to_char() {
+            open.push(spans.len());
+            spans.push(BranchSpan {start: i, end: i, depth: open.len() - 1});
+        }
+        else if c == BranchReturn::to_char() {
+            let branch = open.pop().ok_or("Found a BranchReturn but not a corresponding BranchIndicator.")?;
+            spans[branch].end = i;
+        }
+    }
+
+    if !open.is_empty() {
+        return Err("Found a BranchIndicator but not a corresponding BranchReturn.");
+    }
+
+    Ok(spans)
+}
+
+fn nth_branch(tree_string: &str, n: usize) -> Result<(usize, usize), &'static str> {
+    branch_spans(tree_string)?
+        .get(n)
+        .map(|span| (span.start, span.end))
+        .ok_or("There aren't that many branches in the tree string.")
+}
+
+//Cuts off the nth branch, and everything on it.
+pub fn prune(tree_string: &str, n: usize) -> Result<String, &'static str> {
+    let (start, end) = nth_branch(tree_string, n)?;
+
+    Ok(format!("{}{}", &tree_string[..start], &tree_string[end + 1..]))
+}
+
+//Swaps the nth branch for the mth branch of scion.
+pub fn graft(tree_string: &str, n: usize, scion: &str, m: usize) -> Result<String, &'static str> {
+    let (start, end) = nth_branch(tree_string, n)?;
+    let (scion_start, scion_end) = nth_branch(scion, m)?;
+
+    Ok(format!("{}{}{}", &tree_string[..start], &scion[scion_start..=scion_end], &tree_string[end + 1..]))
+}
+
+//Every leaflet gone, decorations and all, like a dead_tree.
+pub fn strip_leaves(tree_string: &str) -> String {
+    tree_string.chars()
+        .filter(|&c| match Stick::try_from(c) {
+            Ok(Stick::LeafSpawn) | Ok(Stick::LeafReturn) => false,
+            Ok(stick) => !stick.is_leaf() && !stick.is_decoration(),
+            Err(_) => true,
+        })
+        .collect()
+}
+
+//Nothing higher than height cells above the root.
+//A branch stick that would go any higher is cut off with the rest of its branch, or the rest of
+//the tree if it's in the trunk. A leaf is cut off with the rest of its leaflet.
+//Branches left with nothing on them are taken out altogether, as are leaf returns left with
+//nothing to return from.
+pub fn truncate(tree_string: &str, height: usize) -> Result<String, &'static str> {
+    let height = height as i32;
+    let mut output = String::new();
+    let mut cursor = Point {x: 0, y: 0};
+    let mut leaf_spawn_point = cursor;
+    //Where each open branch was, and where it starts in output.
+    let mut branch_points: Vec<(Point, usize)> = Vec::new();
+    //How deep the cut is, and whether it's only a leaflet, while cutting.
+    let mut cut: Option<(usize, bool)> = None;
+
+    for c in tree_string.chars() {
+        let stick = Stick::try_from(c)?;
+        let depth = branch_points.len();
+
+        if let Some((cut_depth, leaflet)) = cut {
+            match stick {
+                Stick::BranchIndicator => {
+                    branch_points.push((cursor, output.len()));
+                    continue;
+                },
+                Stick::BranchReturn if depth > cut_depth => {
+                    branch_points.pop();
+                    continue;
+                },
+                Stick::BranchReturn => cut = None,
+                Stick::LeafReturn if leaflet => cut = None,
+                _ => continue,
+            }
+        }
+
+        match stick {
+            Stick::BranchIndicator => branch_points.push((cursor, output.len())),
+            Stick::BranchReturn => {
+                let (branch_point, start) = branch_points.pop().ok_or("Found a BranchReturn but not a corresponding BranchIndicator.")?;
+                cursor = branch_point;
+                //Nothing left on it but the BranchIndicator.
+                if output.len() == start + 1 {
+                    output.truncate(start);
+                    continue;
+                }
+            },
+            Stick::LeafSpawn => leaf_spawn_point = cursor,
+            Stick::LeafReturn => {
+                cursor = leaf_spawn_point;
+                //Nothing to return from, now the leaves before it are cut off.
+                if output.ends_with(|c| c == Stick::LeafSpawn.to_char() || c == Stick::LeafReturn.to_char()) {
+                    continue;
+                }
+            },
+            stick if stick.is_decoration() => (),
+            stick => {
+                let landing = cursor + stick.cursor_move()?;
+                if landing.y > height {
+                    cut = Some((depth, stick.is_leaf()));
+                    continue;
+                }
+                cursor = landing;
+            },
+        }
+
+        output.push(c);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod edit_tests {
+    use super::*;
+    use crate::{Fvtree, FvtreeString, Config};
+
+    #[test]
+    fn pruning() {
+        assert_eq!(prune("||y|o^ry/y\\ror|", 0), Ok("||y/y\\ror|".to_string()));
+        assert_eq!(prune("||y|o^ry/y\\ror|", 2), Ok("||y|o^ry/or|".to_string()));
+        assert!(prune("||y|o^r|", 1).is_err());
+        assert!(prune("||y|o^", 0).is_err());
+    }
+
+    #[test]
+    fn grafting() {
+        assert_eq!(graft("||y|o^r|", 0, "|y\\y/o<rr", 1), Ok("||y/o<r|".to_string()));
+        assert!(graft("||y|o^r|", 0, "|||", 0).is_err());
+    }
+
+    #[test]
+    fn stripping_leaves() {
+        assert_eq!(strip_leaves("||y|o^@l<ry/o>*r|"), "||y|ry/r|");
+    }
+
+    #[test]
+    fn truncating() {
+        //The trunk, then a branch that's cut halfway up, then one that's gone altogether.
+        assert_eq!(truncate("||y|o^l<ry||o^r|y|r||", 3), Ok("||y|o<ry|r|".to_string()));
+        assert_eq!(truncate("||||", 2), Ok("||".to_string()));
+        assert_eq!(truncate("||", 0), Ok("".to_string()));
+    }
+
+    #[test]
+    fn edits_stay_valid() {
+        for _ in 0..20 {
+            let fvtree_string = FvtreeString::new_recursive_from_config(&Config::new_fruit_tree());
+            let scion = FvtreeString::new();
+            let branches = fvtree_string.tree_string().matches('y').count();
+
+            for n in 0..branches {
+                assert!(Fvtree::build(&fvtree_string.prune(n).unwrap()).is_ok());
+                if scion.tree_string().contains('y') {
+                    assert!(Fvtree::build(&fvtree_string.graft(n, &scion, 0).unwrap()).is_ok());
+                }
+            }
+            assert!(fvtree_string.prune(branches).is_err());
+            assert!(!fvtree_string.strip_leaves().tree_string().contains('o'));
+            assert!(Fvtree::build(&fvtree_string.strip_leaves()).is_ok());
+
+            for height in 0..10 {
+                let truncated = fvtree_string.truncate(height).unwrap();
+                let fvtree = Fvtree::build(&truncated).unwrap();
+                assert!(fvtree.canvas.bounds().is_none_or(|(_, max_corner)| max_corner.y <= height as i32));
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::collections::HashSet;
 use common::Point;
 use text_canvas;
 
@@ -6,9 +7,109 @@ pub struct Fvtree {
     canvas: text_canvas::Canvas,
 }
 
-use std::convert::TryFrom;
-
 impl Fvtree {
+    pub fn build(tree: &FvtreeString) -> Result<Fvtree, &'static str> {
+        Fvtree::build_in_season(tree, Season::Summer)
+    }
+
+    //The same tree at another time of year, see Season.
+    pub fn build_in_season(tree: &FvtreeString, season: Season) -> Result<Fvtree, &'static str> {
+        let mut builder = Builder::new(season);
+
+        for stick in sticks::to_sticks(&tree.tree_string)? {
+            builder.put_down(stick)?;
+        }
+
+        return Ok(Fvtree{canvas: builder.canvas});
+    }
+
+    //The tree growing, a frame every sticks_per_frame sticks, starting from just the root.
+    //Every frame is as big as the whole tree, so they line up when played one after the other.
+    pub fn grow(tree: &FvtreeString, season: Season, sticks_per_frame: usize) -> Result<Growth, &'static str> {
+        let sticks = sticks::to_sticks(&tree.tree_string)?;
+        let whole_tree = Fvtree::build_in_season(tree, season)?;
+
+        Ok(Growth {
+            sticks,
+            next: 0,
+            sticks_per_frame: sticks_per_frame.max(1),
+            builder: Builder::new(season),
+            bounds: whole_tree.canvas.bounds(),
+            started: false,
+            done: false,
+        })
+    }
+
+    //The rendered tree turned around, see text_canvas::Canvas.
+    //To turn the tree string around instead, see FvtreeString::mirror.
+    pub fn flip_horizontal(&mut self) {
+        self.canvas.flip_horizontal();
+    }
+
+    pub fn flip_vertical(&mut self) {
+        self.canvas.flip_vertical();
+    }
+
+    pub fn rotate_90(&mut self) {
+        self.canvas.rotate_90();
+    }
+
+    //Both trees rendered on one canvas, with the cells that differ marked, see diff::visual_diff.
+    pub fn diff(a: &FvtreeString, b: &FvtreeString) -> Result<Fvtree, &'static str> {
+        let a = Fvtree::build(a)?;
+        let b = Fvtree::build(b)?;
+
+        Ok(Fvtree{canvas: diff::visual_diff(&a.canvas, &b.canvas)})
+    }
+
+    //How much the trees look alike, from 0 to 1, see diff::similarity.
+    pub fn similarity(&self, other: &Fvtree) -> f64 {
+        diff::similarity(&self.canvas, &other.canvas)
+    }
+
+    //The tree blowing in the wind, a frame at a time, forever. See WindConfig.
+    //Which leaves come loose and where they drift is down to rng.
+    pub fn sway<R: rand::Rng>(&self, conf: &WindConfig, rng: R) -> Sway<R> {
+        Sway::new(self, conf, rng)
+    }
+}
+
+//Puts a tree down on a canvas a stick at a time.
+struct Builder {
+    canvas: text_canvas::Canvas,
+    season: Season,
+    cursor: Point,
+    branch_points: Vec<Point>,
+    leaf_spawn_point: Option<Point>,
+    //How many leaves have come before, and what the last one looks like, if it's there.
+    num_leaves: usize,
+    leaf: Option<char>,
+    //Where the tree has a Y or an O in summer. Branches skip over these in every season,
+    //whether or not the season draws them, so the branches look the same all year.
+    knots: HashSet<(i32, i32)>,
+}
+
+impl Builder {
+    fn new(season: Season) -> Builder {
+        let mut canvas = text_canvas::Canvas::new();
+        Builder::put_down_root(&mut canvas);
+
+        Builder {
+            canvas,
+            season,
+            cursor: Point {
+                x: 0,
+                y: 0,
+            },
+            branch_points: Vec::new(),
+            leaf_spawn_point: None,
+            num_leaves: 0,
+            leaf: None,
+            //The root.
+            knots: [(0, 0)].iter().copied().collect(),
+        }
+    }
+
     //Put down the root of all fvtrees, which looks like this:
     //
     //    O
@@ -22,83 +123,153 @@ impl Fvtree {
         canvas.put(Point {x: 1, y: -1}, '\\');
     }
 
-    pub fn build(tree: &FvtreeString) -> Result<Fvtree, &'static str> {
-        let mut cursor = Point {
-            x: 0,
-            y: 0,
-        };
-        let mut canvas = text_canvas::Canvas::new();
+    fn put_down(&mut self, stick: Stick) -> Result<(), &'static str> {
+        let canvas = &mut self.canvas;
+        let season = self.season;
 
-        //Convert to Sticks.
-        let mut sticks: Vec<Stick> = Vec::new();
-        for c in tree.tree_string.chars() {
-            let stick = Stick::try_from(c)?;
-            sticks.push(stick);
-        }
+//println!("{}", canvas);
+        if stick.is_control_char() {
+            match stick {
+                Stick::BranchIndicator => {
+//println!("Putting branch down at {:?}.", self.cursor);
+                    canvas.put(self.cursor, 'Y');
+                    self.knots.insert((self.cursor.x, self.cursor.y));
+                    self.branch_points.push(self.cursor);
+                },
+                Stick::BranchReturn => {
+                    self.cursor = self.branch_points.pop().ok_or("Found a BranchReturn but not a corresponding BranchIndicator.")?;
+                },
 
-        Fvtree::put_down_root(&mut canvas);
+                Stick::LeafSpawn => {
+                    self.leaf_spawn_point = Some(self.cursor);
+                    self.leaf = None;
+                    self.knots.insert((self.cursor.x, self.cursor.y));
+                    if let Some(c) = season.leaf_spawn() {
+                        canvas.put(self.cursor, c);
+                    }
+                },
+                Stick::LeafReturn => {
+                    if self.leaf_spawn_point.is_none() {
+                        return Err("Found a LeafReturn but not a corresponding LeafSpawn.");
+                    }
+                    self.cursor = self.leaf_spawn_point.unwrap();
+                    self.leaf = None;
+                },
 
-        let mut branch_points: Vec<Point> = Vec::new();
-        let mut leaf_spawn_point: Option<Point> = None;
+                _ => return Err("Encountered undefined control character?"),
+            }
+        }
+        else if stick.is_decoration() {
+            if self.leaf_spawn_point.is_none() {
+                return Err("Found a decoration but not a corresponding LeafSpawn.");
+            }
 
-        for stick in sticks {
-//println!("{}", canvas);
-            if stick.is_control_char() {
-                match stick {
-                    Stick::BranchIndicator => {
-//println!("Putting branch down at {:?}.", cursor);
-                        canvas.put(cursor, 'Y');
-                        branch_points.push(cursor);
-                    },
-                    Stick::BranchReturn => {
-                        //TODO: rewrite to return err.
-                        cursor = branch_points.pop().expect("Found a BranchReset but not a corresponding BranchIndicator.");
-                    },
-
-                    Stick::LeafSpawn => {
-                        leaf_spawn_point = Some(cursor);
-                        canvas.put(cursor, 'O');
-                    },
-                    Stick::LeafReturn => {
-                        if leaf_spawn_point.is_none() {
-                            return Err("Found a LeafReturn but not a corresponding LeafSpawn.");
-                        }
-                        cursor = leaf_spawn_point.unwrap();
-                    },
-
-                    _ => return Err("Encountered undefined control character?"),
+            //Decorations don't move the cursor, they replace the leaf it's sitting on.
+            //If it isn't sitting on a leaf (e.g. right after a LeafSpawn, or it's fallen),
+            //there's nothing to hang it off of.
+            if let (Some(leaf), Some(decoration)) = (self.leaf, season.decoration(stick)) {
+                if canvas.is_char_point(self.cursor, leaf) {
+                    canvas.put(self.cursor, decoration);
                 }
             }
+        }
+        else {
+            let cursor_move = stick.cursor_move()?;
+
+            //Don't overwrite branch points or seed spawns, unless you are placing a leaf.
+            let landing = self.cursor + cursor_move;
+            if self.knots.contains(&(landing.x, landing.y)) && !stick.is_leaf() {
+                self.cursor += cursor_move;
+                return Ok(());
+            }
+
+            //No longer in leaf-placing state.
+            if !stick.is_leaf() {
+                self.leaf_spawn_point = None;
+            }
             else {
-                let cursor_move = stick.cursor_move()?;
-
-                //Don't overwrite branch points or seed spawns displayed on the canvas,
-                //unless you are placing a leaf.
-                if (canvas.is_char_point(cursor + cursor_move, 'Y') || canvas.is_char_point(cursor + cursor_move, 'O'))
-                    && !stick.is_leaf() {
-                    cursor += cursor_move;
-                    continue;
+                //Put a quote where a leaf would be, unless the season says otherwise.
+                //In summer it's always there, so it's drawn over any knot.
+                self.cursor += cursor_move;
+                self.knots.remove(&(self.cursor.x, self.cursor.y));
+                self.leaf = season.leaf(self.num_leaves);
+                self.num_leaves += 1;
+                if let Some(c) = self.leaf {
+                    canvas.put(self.cursor, c);
                 }
+                return Ok(());
+            }
 
-                //No longer in leaf-placing state.
-                if !stick.is_leaf() {
-                    leaf_spawn_point = None;
-                }
-                else {
-                    //Always put a quote where a leaf would be -- may change later to alias leaves
-                    //into looking like other characters.
-                    cursor += cursor_move;
-                    canvas.put(cursor, '\"');
-                    continue;
-                }
+//println!("Overwriting at {:?} with {}", self.cursor, stick.to_char());
+            self.cursor += cursor_move;
+            canvas.put(self.cursor, stick.to_char());
+        }
+
+        Ok(())
+    }
+}
+
+//The frames of Fvtree::grow.
+pub struct Growth {
+    sticks: Vec<Stick>,
+    next: usize,
+    sticks_per_frame: usize,
+    builder: Builder,
+    //The corners of the whole tree.
+    bounds: Option<(Point, Point)>,
+    //Whether the first frame, just the root, has been given out yet.
+    started: bool,
+    done: bool,
+}
 
-//println!("Overwriting at {:?} with {}", cursor, stick.to_char());
-                cursor += cursor_move;
-                canvas.put(cursor, stick.to_char());
+impl Growth {
+    //Every frame, one after the other, each followed by a line with only a form feed on it,
+    //for players that know nothing about trees.
+    pub fn into_text(self) -> String {
+        let mut text = String::new();
+        for frame in self {
+            text.push_str(&frame.to_string());
+            text.push_str("\x0c\n");
+        }
+
+        text
+    }
+}
+
+impl Iterator for Growth {
+    type Item = Fvtree;
+
+    fn next(&mut self) -> Option<Fvtree> {
+        if self.done {
+            return None;
+        }
+
+        //Branch indicators, returns, leaf spawns and decorations go in the same frame as the
+        //stick before them, like they don't count in confstats::Stats.
+        let counts = |stick: &Stick| !stick.is_control_char() && !stick.is_decoration();
+        let mut sticks = 0;
+        while self.started && self.next < self.sticks.len() {
+            if sticks == self.sticks_per_frame && counts(&self.sticks[self.next]) {
+                break;
             }
+
+            let stick = self.sticks[self.next];
+            self.builder.put_down(stick).expect("Fvtree::grow built the whole tree already.");
+            self.next += 1;
+            if counts(&stick) {
+                sticks += 1;
+            }
+        }
+        self.started = true;
+        self.done = self.next == self.sticks.len();
+
+        let mut canvas = self.builder.canvas.clone();
+        if let Some((min_corner, max_corner)) = self.bounds {
+            canvas.extend_to(min_corner);
+            canvas.extend_to(max_corner);
         }
 
-        return Ok(Fvtree{canvas});
+        Some(Fvtree{canvas})
     }
 }
 
@@ -108,54 +279,328 @@ impl fmt::Display for Fvtree {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FvtreeString {
     tree_string: String,
 }
 
 mod sticks;
 mod confstats;
+mod silhouette;
+mod season;
+mod wind;
+mod edit;
+mod transform;
+mod breed;
+mod diff;
+mod canonical;
+mod trace;
+mod parser;
+mod compact_code;
+mod big_uint;
+mod tree_space;
 
 use crate::sticks::Stick;
-use crate::confstats::{Stats, Config};
+pub use crate::sticks::{LanguageVersion, LANGUAGE_VERSIONS, CURRENT_LANGUAGE_VERSION};
+use crate::confstats::Stats;
+pub use crate::confstats::{Config, TreeConfig, BranchConfig, DepthOverride, DepthConfig, SPECIES};
+pub use crate::silhouette::{Silhouette, SilhouetteShape};
+pub use crate::season::Season;
+pub use crate::wind::{WindConfig, Sway};
+pub use crate::diff::{Difference, Change, Subtree};
+pub use crate::big_uint::BigUint;
+use crate::tree_space::TreeSpace;
 
 mod tree_gen_recursive;
+mod tree_gen_uniform;
+mod tree_gen_lsystem;
+pub use crate::tree_gen_lsystem::{LSystem, LSYSTEM_PRESETS};
+mod tree_gen_colonization;
+pub use crate::tree_gen_colonization::{ColonizationConfig, CrownShape};
 mod choose_amongst;
 
 impl FvtreeString {
+    //Only canonical sticks ever make it into a tree string, so this can't fail.
+    fn sticks(&self) -> Vec<Stick> {
+        sticks::to_sticks(&self.tree_string).expect("Tree strings are always made of canonical Sticks.")
+    }
+
     pub fn new() -> FvtreeString {
         FvtreeString::new_recursive()
     }
 
     pub fn new_recursive() -> FvtreeString {
+        FvtreeString::new_recursive_from_config(&Config::new())
+    }
+
+    pub fn new_recursive_from_config(conf: &Config) -> FvtreeString {
         let mut rng = rand::thread_rng();
-        let conf = Config::new();
         let mut stats = Stats::new();
 
         if conf.t.max_sticks == 0 {
             return FvtreeString{tree_string: "".to_string()};
         }
 
-        let tree_string = tree_gen_recursive::gen(&mut rng, &mut stats, &conf);
+        let tree_string = tree_gen_recursive::gen(&mut rng, &mut stats, conf);
 
         FvtreeString{tree_string: tree_string.to_string()}
     }
 
+    //A tree of one of SPECIES, e.g. "pine".
+    pub fn generate_species(name: &str) -> Result<FvtreeString, &'static str> {
+        let conf = Config::new_species(name).ok_or("There's no species by that name, see fvtree::SPECIES.")?;
+
+        Ok(FvtreeString::new_recursive_from_config(&conf))
+    }
+
+    //Unlike new_recursive(), which favours short trees, every tree exactly len long is as likely
+    //as any other. len counts control characters and all.
+    pub fn new_uniform(len: usize) -> Result<FvtreeString, &'static str> {
+        FvtreeString::new_uniform_from_config(len, &Config::new())
+    }
+
+    //conf can't ask for anything only the recursive generator does, see tree_gen_uniform::check.
+    pub fn new_uniform_from_config(len: usize, conf: &Config) -> Result<FvtreeString, &'static str> {
+        let mut rng = rand::thread_rng();
+        tree_gen_uniform::check(conf)?;
+
+        if conf.t.max_sticks == 0 {
+            return if len == 0 {Ok(FvtreeString{tree_string: "".to_string()})} else {Err("This Config can't grow a tree that long.")};
+        }
+
+        let tree_string = tree_gen_uniform::gen(&mut rng, conf, len)?;
+
+        Ok(FvtreeString{tree_string})
+    }
+
+    //Grows lsystem for generations generations, see LSystem::gen.
+    pub fn new_lsystem(lsystem: &LSystem, generations: usize) -> Result<FvtreeString, &'static str> {
+        let mut rng = rand::thread_rng();
+        let tree_string = lsystem.gen(&mut rng, generations)?;
+
+        Ok(FvtreeString{tree_string})
+    }
+
+    //Grows towards points scattered through the crown, rather than wherever, so branches don't
+    //pile onto each other. See tree_gen_colonization.
+    pub fn new_colonization(conf: &ColonizationConfig) -> FvtreeString {
+        let mut rng = rand::thread_rng();
+        let tree_string = tree_gen_colonization::gen(&mut rng, conf);
+
+        FvtreeString{tree_string}
+    }
+
+    //Grows the tree a little, a stick on the trunk, a new branch and another leaflet, as conf says.
+    //Everything already there stays, and looks the same, so a tree can grow a bit every day and be
+    //kept as a tree string. A tree with no room left to grow stays as it is.
+    pub fn grow<R: rand::Rng>(&mut self, rng: &mut R, conf: &Config) -> Result<(), &'static str> {
+        self.tree_string = tree_gen_recursive::grow(rng, &self.tree_string, conf)?;
+
+        Ok(())
+    }
+
+    //The tree without its nth branch, or anything growing off of it.
+    //Branches are numbered from 0 in the order they come in the tree string.
+    pub fn prune(&self, n: usize) -> Result<FvtreeString, &'static str> {
+        let tree_string = edit::prune(&self.tree_string, n)?;
+
+        Ok(FvtreeString{tree_string})
+    }
+
+    //The tree with its nth branch swapped for the mth branch of scion.
+    pub fn graft(&self, n: usize, scion: &FvtreeString, m: usize) -> Result<FvtreeString, &'static str> {
+        let tree_string = edit::graft(&self.tree_string, n, &scion.tree_string, m)?;
+
+        Ok(FvtreeString{tree_string})
+    }
+
+    //The bare tree, without a leaf or a decoration left on it.
+    pub fn strip_leaves(&self) -> FvtreeString {
+        FvtreeString{tree_string: edit::strip_leaves(&self.tree_string)}
+    }
+
+    //The tree a little different, with each branch and leaf changed rate times out of 100,
+    //see breed::mutate.
+    pub fn mutate(&self, rng: &mut rand::rngs::ThreadRng, rate: usize) -> FvtreeString {
+        let tree_string = breed::mutate(rng, &self.sticks(), rate);
+
+        FvtreeString{tree_string}
+    }
+
+    //Two children of a and b, each with a branch from the other, see breed::crossover.
+    pub fn crossover(a: &FvtreeString, b: &FvtreeString, rng: &mut rand::rngs::ThreadRng) -> Result<(FvtreeString, FvtreeString), &'static str> {
+        let (child_a, child_b) = breed::crossover(rng, &a.tree_string, &b.tree_string)?;
+
+        Ok((FvtreeString{tree_string: child_a}, FvtreeString{tree_string: child_b}))
+    }
+
+    //The branches and leaflets that have been taken out of this tree and put into other,
+    //see diff::diff.
+    pub fn diff(&self, other: &FvtreeString) -> Result<Vec<Difference>, &'static str> {
+        diff::diff(&self.tree_string, &other.tree_string)
+    }
+
+    //How much the trees look alike once they're rendered, from 0 to 1, see diff::similarity.
+    pub fn similarity(&self, other: &FvtreeString) -> Result<f64, &'static str> {
+        Ok(Fvtree::build(self)?.similarity(&Fvtree::build(other)?))
+    }
+
+    //The same tree, as it renders in every season, with no-op sticks taken out and branches put in
+    //order, see canonical::canonicalize. Errors if the tree can't be built.
+    pub fn canonicalize(&self) -> Result<FvtreeString, &'static str> {
+        let tree_string = canonical::canonicalize(&self.tree_string)?;
+        Fvtree::build(self)?;
+
+        Ok(FvtreeString{tree_string})
+    }
+
+    //A hash of the tree string that stays the same from one run, or version of Rust, to the next,
+    //for keeping trees around by. Canonicalize first, so trees that render the same hash the same.
+    pub fn content_hash(&self) -> u64 {
+        canonical::content_hash(&self.tree_string)
+    }
+
+    //The tree in a picture, the other way from Fvtree::build, see trace::trace.
+    //The picture needs the /|\ under the root, and can be anything else Fvtree::build draws.
+    pub fn trace(art: &str) -> Result<Tracing, &'static str> {
+        trace::trace(&trace::cells_of_art(art))
+    }
+
+    pub fn trace_canvas(canvas: &text_canvas::Canvas) -> Result<Tracing, &'static str> {
+        trace::trace(&trace::cells_of_canvas(canvas))
+    }
+
+    //The tree facing the other way, so two of them can stand either side of something.
+    pub fn mirror(&self) -> FvtreeString {
+        let tree_string = self.sticks().iter().map(|stick| stick.mirrored().to_char()).collect();
+
+        FvtreeString{tree_string}
+    }
+
+    //The tree twice the size, see transform::scale_up.
+    pub fn scale_up(&self) -> FvtreeString {
+        let tree_string = transform::scale_up(&self.sticks());
+
+        FvtreeString{tree_string}
+    }
+
+    //The tree leaning percent percent of its height over to the right, or the left if percent is
+    //negative, see transform::lean.
+    pub fn lean(&self, percent: i32) -> Result<FvtreeString, &'static str> {
+        let tree_string = transform::lean(&self.tree_string, percent)?;
+
+        Ok(FvtreeString{tree_string})
+    }
+
+    //The tree cut down so nothing's more than height above the root.
+    pub fn truncate(&self, height: usize) -> Result<FvtreeString, &'static str> {
+        let tree_string = edit::truncate(&self.tree_string, height)?;
+
+        Ok(FvtreeString{tree_string})
+    }
+
     //TODO: proper recursive descent parser, but for now this will do.
-    pub fn build(tree_string: &str) -> Result<FvtreeString, &'static String> {
-        Ok(FvtreeString{tree_string: tree_string.to_string()})
+    //Tree strings without a version header are assumed to be v1, and are migrated to the
+    //current version.
+    pub fn build(tree_string: &str) -> Result<FvtreeString, &'static str> {
+        let tree_string = parser::parse(tree_string)?;
+
+        Ok(FvtreeString{tree_string})
     }
 
     //pub fn build_procedural(config: &TreeConfig) -> Fvtree {
     //}
 
+    //The tree string without its version header.
     pub fn tree_string(&self) -> String {
         self.tree_string.to_string()
     }
+
+    //A short, URL-safe code for the tree string, made of base64url characters.
+    pub fn to_compact_code(&self) -> String {
+        compact_code::encode(&self.sticks())
+    }
+
+    pub fn from_compact_code(code: &str) -> Result<FvtreeString, &'static str> {
+        let tree_string = compact_code::decode(code)?;
+
+        Ok(FvtreeString{tree_string})
+    }
+
+    //Every tree a Config can grow has a number, and this is the tree numbered index.
+    //Numbers are only permanent for as long as the Config doesn't change.
+    pub fn from_index(index: &BigUint, conf: &Config) -> Result<FvtreeString, &'static str> {
+        let tree_string = TreeSpace::new(conf).tree_at(index)?;
+
+        Ok(FvtreeString{tree_string})
+    }
+
+    //The number of this tree among every tree conf can grow, the inverse of from_index().
+    //Errors if conf couldn't have grown it.
+    pub fn index(&self, conf: &Config) -> Result<BigUint, &'static str> {
+        TreeSpace::new(conf).index_of(&self.tree_string)
+    }
+
+    //How many trees conf can grow of each length up to max_len, control characters and all.
+    //Trees are measured by length rather than by sticks, since there's no end to the trees with
+    //the same number of sticks when branches can be bare. When they can't, see count_up_to_sticks().
+    pub fn count_up_to(max_len: usize, conf: &Config) -> Vec<BigUint> {
+        let mut tree_space = TreeSpace::new(conf);
+
+        (0..=max_len).map(|len| tree_space.count(len)).collect()
+    }
+
+    //Every tree conf can grow up to max_len long, in the order from_index() numbers them.
+    pub fn enumerate(max_len: usize, conf: &Config) -> FvtreeStrings<'_> {
+        FvtreeStrings{trees: TreeSpace::new(conf).trees_up_to(max_len)}
+    }
+
+    //How many trees conf can grow with up to max_sticks sticks, of each length up to the longest.
+    //Errors if there's no end to them, when branches can be bare.
+    pub fn count_up_to_sticks(max_sticks: usize, conf: &Config) -> Result<Vec<BigUint>, &'static str> {
+        let mut tree_space = TreeSpace::new_up_to_sticks(conf, max_sticks)?;
+        let max_len = tree_space.max_len().expect("There's an end to them, or there'd be an Err.");
+
+        Ok((0..=max_len).map(|len| tree_space.count(len)).collect())
+    }
+
+    //Every tree conf can grow with up to max_sticks sticks, shortest first.
+    //They aren't in the order from_index() numbers them, see TreeSpace::new_up_to_sticks().
+    pub fn enumerate_up_to_sticks(max_sticks: usize, conf: &Config) -> Result<FvtreeStrings<'_>, &'static str> {
+        Ok(FvtreeStrings{trees: TreeSpace::new_up_to_sticks(conf, max_sticks)?.trees_up_to(usize::MAX)})
+    }
+
+    //The same tree string, shortened with repetition counts and macros for storage and sharing.
+    //Includes the version header, and reads back with build().
+    pub fn compact(&self) -> String {
+        parser::version_header(CURRENT_LANGUAGE_VERSION) + &parser::compact(&self.tree_string)
+    }
+}
+
+pub struct FvtreeStrings<'a> {
+    trees: tree_space::Trees<'a>,
+}
+
+impl Iterator for FvtreeStrings<'_> {
+    type Item = FvtreeString;
+
+    fn next(&mut self) -> Option<FvtreeString> {
+        self.trees.next().map(|tree_string| FvtreeString{tree_string})
+    }
+}
+
+//A tree string traced back from a picture of a tree, see FvtreeString::trace.
+pub struct Tracing {
+    pub fvtree_string: FvtreeString,
+    //Every cell where the tree doesn't look like the picture, with the root at (0, 0):
+    //what's there in the picture, then what's there in the tree, built in summer.
+    pub differences: Vec<(Point, Option<char>, Option<char>)>,
 }
 
+//Always written with a version header, so it can be read back the same way later.
 impl fmt::Display for FvtreeString {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.tree_string)
+        write!(f, "{}{}", parser::version_header(CURRENT_LANGUAGE_VERSION), self.tree_string)
     }
 }
 
@@ -167,4 +612,58 @@ mod fvtree_tests {
     fn dont_panic() {
         assert!(true);
     }
+
+    #[test]
+    fn decorations_replace_leaf_tips() {
+        let tree_string = FvtreeString::build("v2:||yo^@>*l<%r").unwrap();
+        let output = Fvtree::build(&tree_string).unwrap().to_string();
+        assert!(output.contains('@'));
+        assert!(output.contains('*'));
+        assert!(output.contains('%'));
+        assert!(!output.contains('\"'));
+    }
+
+    #[test]
+    fn growing() {
+        let tree_string = FvtreeString::build("v2:|\\|yo^@l>lr/").unwrap();
+        let whole_tree = Fvtree::build(&tree_string).unwrap().to_string();
+
+        //Frames are padded out to the size of the whole tree.
+        let trim = |frame: &str| frame.lines().map(|line| line.trim_end().to_string() + "\n").collect::<String>();
+
+        let frames: Vec<String> = Fvtree::grow(&tree_string, Season::Summer, 1).unwrap().map(|frame| frame.to_string()).collect();
+        //Just the root, then a frame for each of the 6 sticks.
+        assert_eq!(frames.len(), 7);
+        assert_eq!(frames[0].trim(), "O\n/|\\");
+        assert_eq!(trim(&frames[6]), whole_tree);
+        assert!(frames.iter().all(|frame| frame.lines().count() == whole_tree.lines().count()));
+
+        let frames: Vec<Fvtree> = Fvtree::grow(&tree_string, Season::Summer, 4).unwrap().collect();
+        assert_eq!(frames.len(), 3);
+
+        let text = Fvtree::grow(&tree_string, Season::Summer, 2).unwrap().into_text();
+        let text_frames: Vec<&str> = text.split_terminator("\x0c\n").collect();
+        assert_eq!(text_frames.len(), 4);
+        assert_eq!(trim(text_frames[3]), whole_tree);
+    }
+
+    #[test]
+    fn decoration_outside_leaflet() {
+        let tree_string = FvtreeString::build("v2:||@").unwrap();
+        assert!(Fvtree::build(&tree_string).is_err());
+    }
+
+    #[test]
+    fn branch_return_outside_branch() {
+        let tree_string = FvtreeString::build("v2:|r").unwrap();
+        assert!(Fvtree::build(&tree_string).is_err());
+        assert!(Fvtree::grow(&tree_string, Season::Summer, 1).is_err());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let fvtree_string = FvtreeString::new();
+        let read_back = FvtreeString::build(&fvtree_string.to_string()).unwrap();
+        assert_eq!(fvtree_string.tree_string(), read_back.tree_string());
+    }
 }
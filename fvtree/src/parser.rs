@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::sticks;
+
+//Every tree string header looks like "v2:".
+const VERSION_HEADER_START: char = 'v';
+const VERSION_HEADER_END: char = ':';
+
+//Macros look like "[A=o^<l]", and are used by name afterwards, e.g. "yA|A".
+const DEFINITION_START: char = '[';
+const DEFINITION_ASSIGN: char = '=';
+const DEFINITION_END: char = ']';
+
+//What a definition costs on top of its body, i.e. "[A=" and "]".
+const DEFINITION_OVERHEAD: usize = 4;
+
+//So a tree string like "999999999999|" can't eat all the memory.
+pub const MAX_EXPANDED_STICKS: usize = 1_000_000;
+
+//Don't bother looking for macros longer than this, in tokens, when compacting.
+const MAX_MACRO_TOKENS: usize = 32;
+
+fn is_macro_name(c: char) -> bool {
+    c.is_ascii_uppercase()
+}
+
+//Split a tree string into the language version it was written in and its sticks.
+//A missing header means the string is from before headers existed, i.e. v1.
+//
+//'v' is also a DownLeaf, but a DownLeaf is never followed by a digit, so there's no confusion.
+pub fn split_version_header(input: &str) -> Result<(u32, &str), &'static str> {
+    let after_start = match input.strip_prefix(VERSION_HEADER_START) {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+        _ => return Ok((1, input)),
+    };
+
+    let end = match after_start.find(VERSION_HEADER_END) {
+        Some(i) => i,
+        None => return Err("Tree string header is missing its ':'."),
+    };
+
+    let version = match after_start[..end].parse::<u32>() {
+        Ok(version) => version,
+        Err(_) => return Err("Tree string header has an invalid version number."),
+    };
+
+    Ok((version, &after_start[end + 1..]))
+}
+
+pub fn version_header(version: u32) -> String {
+    format!("{}{}{}", VERSION_HEADER_START, version, VERSION_HEADER_END)
+}
+
+//Parse a tree string, with or without a header, into a current version tree string,
+//i.e. nothing but sticks.
+pub fn parse(input: &str) -> Result<String, &'static str> {
+    let (version, tree_string) = split_version_header(input)?;
+
+    let has_macros = sticks::language_version(version).is_some_and(|v| v.has_macros);
+    if has_macros {
+        let expanded = Expander::new(tree_string).expand()?;
+        return sticks::migrate(&expanded, version);
+    }
+
+    sticks::migrate(tree_string, version)
+}
+
+//Recursive descent over the repetition and macro syntax:
+//
+//tree_string := (definition | repetition)*
+//definition  := '[' NAME '=' repetition* ']'
+//repetition  := count? (STICK | NAME)
+//count       := DIGIT+
+//
+//A macro can only be used after it is defined, which also rules out recursive macros.
+struct Expander<'a> {
+    chars: Peekable<Chars<'a>>,
+    macros: HashMap<char, String>,
+}
+
+impl Expander<'_> {
+    fn new(tree_string: &str) -> Expander<'_> {
+        Expander {
+            chars: tree_string.chars().peekable(),
+            macros: HashMap::new(),
+        }
+    }
+
+    fn expand(mut self) -> Result<String, &'static str> {
+        let mut output = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if c == DEFINITION_START {
+                self.chars.next();
+                self.definition()?;
+            }
+            else {
+                self.repetition(&mut output)?;
+            }
+        }
+
+        Ok(output)
+    }
+
+    //The opening '[' has already been consumed.
+    fn definition(&mut self) -> Result<(), &'static str> {
+        let name = match self.chars.next() {
+            Some(c) if is_macro_name(c) => c,
+            _ => return Err("Expected a macro name, A to Z, after '['."),
+        };
+
+        if self.chars.next() != Some(DEFINITION_ASSIGN) {
+            return Err("Expected '=' after the macro name.");
+        }
+
+        if self.macros.contains_key(&name) {
+            return Err("Found a macro defined more than once.");
+        }
+
+        let mut body = String::new();
+        loop {
+            match self.chars.peek() {
+                Some(&DEFINITION_END) => {
+                    self.chars.next();
+                    break;
+                },
+                Some(&DEFINITION_START) => return Err("Macros can't be defined inside other macros."),
+                Some(_) => self.repetition(&mut body)?,
+                None => return Err("Found a macro definition without its ']'."),
+            }
+        }
+
+        self.macros.insert(name, body);
+        Ok(())
+    }
+
+    fn repetition(&mut self, output: &mut String) -> Result<(), &'static str> {
+        let count = self.count()?;
+
+        let unit = match self.chars.next() {
+            Some(c) if is_macro_name(c) => match self.macros.get(&c) {
+                Some(body) => body.to_string(),
+                None => return Err("Found a macro used before it was defined."),
+            },
+            Some(c) if c == DEFINITION_ASSIGN || c == DEFINITION_END || c == DEFINITION_START => {
+                return Err("Expected a stick or macro, found part of a macro definition.");
+            },
+            Some(c) => c.to_string(),
+            None => return Err("Expected a stick or macro after the repetition count."),
+        };
+
+        if output.len().saturating_add(unit.len().saturating_mul(count)) > MAX_EXPANDED_STICKS {
+            return Err("Tree string expands into too many sticks.");
+        }
+
+        for _ in 0..count {
+            output.push_str(&unit);
+        }
+
+        Ok(())
+    }
+
+    //No count means it appears once.
+    fn count(&mut self) -> Result<usize, &'static str> {
+        let mut digits = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            digits.push(c);
+            self.chars.next();
+        }
+
+        if digits.is_empty() {
+            return Ok(1);
+        }
+
+        match digits.parse::<usize>() {
+            Ok(count) if count > 0 => Ok(count),
+            _ => Err("Found an invalid repetition count."),
+        }
+    }
+}
+
+//A stick or macro name, and how many times in a row it appears.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+struct Token {
+    count: usize,
+    unit: char,
+}
+
+impl Token {
+    //Twice in a row costs the same either way, so only bother with a count from three on.
+    fn encoded_len(&self) -> usize {
+        if self.count < 3 {
+            self.count
+        }
+        else {
+            self.count.to_string().len() + 1
+        }
+    }
+
+    fn encode(&self, output: &mut String) {
+        if self.count < 3 {
+            for _ in 0..self.count {
+                output.push(self.unit);
+            }
+        }
+        else {
+            output.push_str(&self.count.to_string());
+            output.push(self.unit);
+        }
+    }
+}
+
+fn encode_tokens(tokens: &[Token]) -> String {
+    let mut output = String::new();
+    for token in tokens {
+        token.encode(&mut output);
+    }
+    output
+}
+
+//Run-length encode, merging neighbouring tokens of the same unit.
+fn merge_runs(tokens: Vec<Token>) -> Vec<Token> {
+    let mut merged: Vec<Token> = Vec::new();
+
+    for token in tokens {
+        match merged.last_mut() {
+            Some(last) if last.unit == token.unit => last.count += token.count,
+            _ => merged.push(token),
+        }
+    }
+
+    merged
+}
+
+fn encoded_len(tokens: &[Token]) -> usize {
+    tokens.iter().map(|t| t.encoded_len()).sum()
+}
+
+//Find the run of tokens that saves the most characters when pulled out into a macro.
+//
+//How much a run saves is only known for sure once it's been replaced, since its uses can merge
+//into a count, e.g. "/\\/\\/\\" is "[A=/\\]3A". So runs are first guessed at without merging,
+//and only the ones that look like they pay for themselves are tried for real.
+fn best_macro(tokens: &[Token]) -> Option<Vec<Token>> {
+    let current_len = encoded_len(tokens);
+    let mut best: Option<(Vec<Token>, usize)> = None;
+
+    for len in 2..=MAX_MACRO_TOKENS.min(tokens.len() / 2) {
+        //Each distinct run in order of first appearance, with how many times it appears without
+        //overlapping and where the last counted appearance ends.
+        //Kept in order so ties always go the same way.
+        let mut runs: Vec<(&[Token], usize, usize)> = Vec::new();
+        let mut run_indices: HashMap<&[Token], usize> = HashMap::new();
+
+        for start in 0..=tokens.len() - len {
+            let run = &tokens[start..start + len];
+            let i = *run_indices.entry(run).or_insert_with(|| {
+                runs.push((run, 0, 0));
+                runs.len() - 1
+            });
+
+            let (_, times, end) = &mut runs[i];
+            if *times == 0 || start >= *end {
+                *times += 1;
+                *end = start + len;
+            }
+        }
+
+        for (run, times, _) in runs {
+            let run_len = encoded_len(run);
+            if times * run_len <= times + run_len + DEFINITION_OVERHEAD {
+                continue;
+            }
+
+            //The name doesn't matter here, they're all one character.
+            let after = run_len + DEFINITION_OVERHEAD + encoded_len(&replace_runs(tokens, run, 'A'));
+            if after < current_len && best.as_ref().is_none_or(|(_, saved)| current_len - after > *saved) {
+                best = Some((run.to_vec(), current_len - after));
+            }
+        }
+    }
+
+    best.map(|(run, _)| run)
+}
+
+fn replace_runs(tokens: &[Token], run: &[Token], name: char) -> Vec<Token> {
+    let mut replaced = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        if tokens[i..].starts_with(run) {
+            replaced.push(Token {count: 1, unit: name});
+            i += run.len();
+        }
+        else {
+            replaced.push(tokens[i]);
+            i += 1;
+        }
+    }
+
+    merge_runs(replaced)
+}
+
+//A compact form of a tree string (without its header), using repetition counts and then greedily
+//pulling the most profitable runs out into macros, one at a time.
+//Never longer than the tree string itself, but not always the shortest there is. Finding that means
+//trying every set of macros, and a run that saves the most now can break up two that would have
+//saved more between them.
+pub fn compact(tree_string: &str) -> String {
+    let mut tokens = merge_runs(tree_string.chars().map(|unit| Token {count: 1, unit}).collect());
+    let mut definitions = String::new();
+
+    for name in 'A'..='Z' {
+        let run = match best_macro(&tokens) {
+            Some(best) => best,
+            None => break,
+        };
+
+        definitions.push(DEFINITION_START);
+        definitions.push(name);
+        definitions.push(DEFINITION_ASSIGN);
+        definitions.push_str(&encode_tokens(&run));
+        definitions.push(DEFINITION_END);
+
+        tokens = replace_runs(&tokens, &run, name);
+    }
+
+    let compacted = definitions + &encode_tokens(&tokens);
+    if compacted.len() < tree_string.len() {
+        compacted
+    }
+    else {
+        tree_string.to_string()
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use super::*;
+    use crate::FvtreeString;
+
+    #[test]
+    fn headerless_is_v1() {
+        assert_eq!(split_version_header("||y|r"), Ok((1, "||y|r")));
+        assert_eq!(split_version_header("v|y|r"), Ok((1, "v|y|r")));
+        assert_eq!(split_version_header(""), Ok((1, "")));
+    }
+
+    #[test]
+    fn header() {
+        assert_eq!(split_version_header("v2:||y|r"), Ok((2, "||y|r")));
+        assert_eq!(split_version_header("v12:"), Ok((12, "")));
+        assert!(split_version_header("v2||").is_err());
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let header = version_header(sticks::CURRENT_LANGUAGE_VERSION) + "||";
+        assert_eq!(parse(&header), Ok("||".to_string()));
+    }
+
+    #[test]
+    fn decorations_need_v2() {
+        assert!(parse("||yo^@lr").is_err());
+        assert!(parse("v2:||yo^@lr").is_ok());
+    }
+
+    #[test]
+    fn macros_need_v3() {
+        assert!(parse("v2:12|").is_err());
+        assert_eq!(parse("v3:12|"), Ok("||||||||||||".to_string()));
+    }
+
+    #[test]
+    fn expand_macros() {
+        assert_eq!(parse("v3:[A=o^<l]2|yA3/Ar"), Ok("||yo^<l///o^<lr".to_string()));
+        assert_eq!(parse("v3:[A=2/][B=A|]yBr2B"), Ok("y//|r//|//|".to_string()));
+    }
+
+    #[test]
+    fn bad_macros() {
+        assert!(parse("v3:A").is_err());
+        assert!(parse("v3:[A=|][A=/]").is_err());
+        assert!(parse("v3:[A=[B=|]]").is_err());
+        assert!(parse("v3:[A=|").is_err());
+        assert!(parse("v3:0|").is_err());
+        assert!(parse("v3:3").is_err());
+        assert!(parse("v3:]").is_err());
+        assert!(parse("v3:999999999999|").is_err());
+    }
+
+    #[test]
+    fn compact_runs() {
+        assert_eq!(compact("||||||||||||"), "12|");
+        assert_eq!(compact("||"), "||");
+        assert_eq!(compact(""), "");
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        let tree_string = "||y/\\o^<lt<l>>>r/y\\o^<lt<l>>>r|y|o^<lt<l>>>r";
+        let compacted = compact(tree_string);
+        assert!(compacted.len() < tree_string.len());
+        assert_eq!(parse(&(version_header(3) + &compacted)), Ok(tree_string.to_string()));
+    }
+
+    #[test]
+    fn compacting_merges_macro_uses() {
+        //"/\/\" appears the most times without overlapping, but "/\" merges into a single count.
+        assert_eq!(compact("/\\/\\/\\/\\/\\/\\/\\/\\"), "[A=/\\]8A");
+    }
+
+    #[test]
+    fn compacting_is_greedy() {
+        //Pulling out "\/<</" first saves the most, but leaves nothing else worth a macro.
+        let tree_string = "^^<\\/<</\\/<</\\^^<\\^^<\\/<</\\";
+        let compacted = compact(tree_string);
+        assert_eq!(compacted, "[A=\\/<</]^^<AA\\^^<\\^^<A\\");
+
+        let shortest = "[A=^^<\\][B=/<</\\]ABBAAB";
+        assert!(shortest.len() < compacted.len());
+        assert_eq!(parse(&(version_header(3) + shortest)), Ok(tree_string.to_string()));
+        assert_eq!(parse(&(version_header(3) + &compacted)), Ok(tree_string.to_string()));
+    }
+
+    #[test]
+    fn compacted_trees_read_back() {
+        for _ in 0..20 {
+            let fvtree_string = FvtreeString::new();
+            let compacted = fvtree_string.compact();
+            assert!(compacted.len() <= fvtree_string.to_string().len());
+
+            let read_back = FvtreeString::build(&compacted).unwrap();
+            assert_eq!(fvtree_string.tree_string(), read_back.tree_string());
+        }
+    }
+}
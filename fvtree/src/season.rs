@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use crate::sticks::Stick;
+
+//The time of year to render a tree in, see Fvtree::build_in_season.
+//Seasons only change how a tree looks, not its tree string, so one tree can be shown all year.
+//Which leaves blossom or fall goes by their order in the tree string, not chance,
+//so the same tree looks the same every time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Season {
+    //Every other leaf is a blossom, and whatever hangs off of the rest blossoms too.
+    Spring,
+    //Full foliage, the tree as it was grown.
+    Summer,
+    //Every other leaf has fallen and the rest have turned.
+    //Flowers and blossoms are gone, but the fruit's still there.
+    Autumn,
+    //Bare, like a dead_tree, not even the leaf spawns are left.
+    Winter,
+}
+
+const LEAF_SPAWN_GLYPH: char = 'O';
+const LEAF_GLYPH: char = '\"';
+//There's no colour on the canvas, so a leaf that's turned gets a glyph of its own instead.
+const TURNED_LEAF_GLYPH: char = '\'';
+
+impl Season {
+    //What the nth leaf of the tree looks like, or None if it isn't there.
+    pub fn leaf(&self, nth: usize) -> Option<char> {
+        match self {
+            Season::Spring if nth.is_multiple_of(2) => Some(Stick::Blossom.to_char()),
+            Season::Spring | Season::Summer => Some(LEAF_GLYPH),
+            Season::Autumn if !nth.is_multiple_of(2) => None,
+            Season::Autumn => Some(TURNED_LEAF_GLYPH),
+            Season::Winter => None,
+        }
+    }
+
+    //What decoration looks like hung off of a leaf, or None if it isn't there.
+    pub fn decoration(&self, decoration: Stick) -> Option<char> {
+        match (self, decoration) {
+            (Season::Spring, _) => Some(Stick::Blossom.to_char()),
+            (Season::Summer, _) | (Season::Autumn, Stick::Fruit) => Some(decoration.to_char()),
+            (Season::Autumn, _) | (Season::Winter, _) => None,
+        }
+    }
+
+    //What a leaf spawn looks like, or None if it isn't there.
+    pub fn leaf_spawn(&self) -> Option<char> {
+        match self {
+            Season::Winter => None,
+            _ => Some(LEAF_SPAWN_GLYPH),
+        }
+    }
+}
+
+impl FromStr for Season {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Season, &'static str> {
+        match s {
+            "spring" => Ok(Season::Spring),
+            "summer" => Ok(Season::Summer),
+            "autumn" | "fall" => Ok(Season::Autumn),
+            "winter" => Ok(Season::Winter),
+            _ => Err("Seasons are spring, summer, autumn or winter."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod season_tests {
+    use super::*;
+    use crate::{Fvtree, FvtreeString};
+
+    #[test]
+    fn seasons() {
+        let tree_string = FvtreeString::build("v2:||yo^@l>*l<%lur").unwrap();
+        let in_season = |season| Fvtree::build_in_season(&tree_string, season).unwrap().to_string();
+
+        assert_eq!(in_season(Season::Summer), Fvtree::build(&tree_string).unwrap().to_string());
+
+        let spring = in_season(Season::Spring);
+        //The first and third leaves, and the flower on the second.
+        assert_eq!(spring.matches('%').count(), 3);
+        assert!(spring.contains('\"') && !spring.contains('@') && !spring.contains('*'));
+
+        //The second and fourth leaves have fallen, the fruit on the first hasn't.
+        let autumn = in_season(Season::Autumn);
+        assert!(autumn.contains('@') && autumn.contains('\''));
+        assert!(!autumn.contains('\"') && !autumn.contains('*') && !autumn.contains('%'));
+
+        let winter = in_season(Season::Winter);
+        //Nothing but sticks, and the root.
+        assert!(winter.chars().all(|c| "|/\\YO \n".contains(c)), "{}", winter);
+        assert_eq!(winter.matches('O').count(), 1);
+
+        //The trunk goes around the leaf spawn the branch ends in, even where it isn't drawn,
+        //so the branch underneath still shows.
+        let tree_string = FvtreeString::build("v2:|y\\/o<lr||").unwrap();
+        let top_line = |season| {
+            Fvtree::build_in_season(&tree_string, season).unwrap().to_string().lines().next().map(|line| line.trim_end().to_string())
+        };
+        assert_eq!(top_line(Season::Summer), Some("\"O".to_string()));
+        assert_eq!(top_line(Season::Winter), Some(" /".to_string()));
+
+        assert_eq!("fall".parse::<Season>(), Ok(Season::Autumn));
+        assert!("monsoon".parse::<Season>().is_err());
+    }
+}
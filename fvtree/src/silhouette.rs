@@ -0,0 +1,140 @@
+use std::str::FromStr;
+
+use common::Point;
+
+use crate::sticks::Stick;
+
+//The outline a tree should fill, for when it has to fit somewhere in particular.
+//Sizes are in canvas cells, and the root is at (0, 0), in the middle of the bottom.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SilhouetteShape {
+    //A round crown on a short trunk.
+    Round,
+    //Tall and narrow, no wider at the top than the bottom.
+    Columnar,
+    //Wide at the bottom, a point at the top.
+    Conical,
+    //Branches can't grow down, so weeping trees spread out towards the top instead,
+    //and hang their leaves down.
+    Weeping,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Silhouette {
+    pub shape: SilhouetteShape,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Silhouette {
+    //A sensible size for each shape.
+    pub fn new(shape: SilhouetteShape) -> Silhouette {
+        let (width, height) = match shape {
+            SilhouetteShape::Round => (25, 16),
+            SilhouetteShape::Columnar => (7, 24),
+            SilhouetteShape::Conical => (25, 24),
+            SilhouetteShape::Weeping => (31, 14),
+        };
+
+        Silhouette {shape, width, height}
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        let half_width = (self.width / 2) as f64;
+        let height = self.height.max(1) as f64;
+        let (x, y) = ((point.x as f64).abs(), point.y as f64);
+
+        //Leaves can hang a little below the root, but that's all.
+        if y < -1.0 || y > height || x > half_width {
+            return false;
+        }
+
+        match self.shape {
+            SilhouetteShape::Round => {
+                //The crown takes up the top two thirds, the trunk's underneath it.
+                let (centre, radius) = (height * 2.0 / 3.0, height / 3.0);
+                let (dx, dy) = (x / half_width.max(1.0), (y - centre) / radius);
+                (x <= 1.0 && y <= centre) || dx * dx + dy * dy <= 1.0
+            },
+            SilhouetteShape::Columnar => true,
+            //One cell of slack all the way up, so the trunk always fits.
+            SilhouetteShape::Conical => x <= 1.0 + half_width * (height - y) / height,
+            SilhouetteShape::Weeping => x <= 1.0 + half_width * y / height,
+        }
+    }
+
+    pub fn grows_leaf(&self, leaf: Stick) -> bool {
+        match self.shape {
+            SilhouetteShape::Weeping => !matches!(leaf, Stick::UpLeaf | Stick::UpLeftLeaf | Stick::UpRightLeaf),
+            _ => true,
+        }
+    }
+}
+
+//"round", or "round:25x16" for a particular width and height.
+impl FromStr for Silhouette {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Silhouette, &'static str> {
+        let (shape, size) = match s.split_once(':') {
+            Some((shape, size)) => (shape, Some(size)),
+            None => (s, None),
+        };
+
+        let shape = match shape {
+            "round" => SilhouetteShape::Round,
+            "columnar" => SilhouetteShape::Columnar,
+            "conical" => SilhouetteShape::Conical,
+            "weeping" => SilhouetteShape::Weeping,
+            _ => return Err("Silhouettes are round, columnar, conical or weeping."),
+        };
+
+        let mut silhouette = Silhouette::new(shape);
+        if let Some(size) = size {
+            let parsed = size.split_once('x')
+                .and_then(|(width, height)| Some((width.parse::<usize>().ok()?, height.parse::<usize>().ok()?)));
+
+            match parsed {
+                Some((width, height)) => {
+                    silhouette.width = width;
+                    silhouette.height = height;
+                },
+                None => return Err("Silhouette sizes look like 25x16, width then height."),
+            }
+        }
+
+        Ok(silhouette)
+    }
+}
+
+#[cfg(test)]
+mod silhouette_tests {
+    use super::*;
+
+    #[test]
+    fn shapes() {
+        let point = |x, y| Point {x, y};
+
+        let round = Silhouette::new(SilhouetteShape::Round);
+        assert!(round.contains(point(0, 1)));
+        assert!(!round.contains(point(6, 1)));
+        assert!(round.contains(point(10, 11)));
+
+        let conical = Silhouette::new(SilhouetteShape::Conical);
+        assert!(conical.contains(point(10, 2)));
+        assert!(!conical.contains(point(10, 20)));
+
+        let weeping = Silhouette::new(SilhouetteShape::Weeping);
+        assert!(!weeping.contains(point(10, 2)));
+        assert!(weeping.contains(point(10, 12)));
+        assert!(!weeping.grows_leaf(Stick::UpLeaf));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("columnar".parse(), Ok(Silhouette::new(SilhouetteShape::Columnar)));
+        assert_eq!("round:9x5".parse(), Ok(Silhouette {shape: SilhouetteShape::Round, width: 9, height: 5}));
+        assert!("round:9".parse::<Silhouette>().is_err());
+        assert!("blob".parse::<Silhouette>().is_err());
+    }
+}
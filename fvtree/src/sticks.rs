@@ -3,7 +3,7 @@ use std::fmt;
 
 use common::Point;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Stick {
     UpBranch,
     UpLeftBranch,
@@ -24,6 +24,10 @@ pub enum Stick {
 
     LeafSpawn,
     LeafReturn,
+
+    Fruit,
+    Flower,
+    Blossom,
 }
 
 //God this is dumb, there's definitely a better way to do this. (So why don't I take it?)
@@ -51,6 +55,10 @@ macro_rules! match_func_on_each_variant {
 
             Stick::LeafSpawn => LeafSpawn::$func(),
             Stick::LeafReturn => LeafReturn::$func(),
+
+            Stick::Fruit => Fruit::$func(),
+            Stick::Flower => Flower::$func(),
+            Stick::Blossom => Blossom::$func(),
         }
     }
 }
@@ -87,12 +95,65 @@ impl Stick {
     }
 
     pub fn cursor_move(&self) -> Result<Point, &'static str> {
-        match_func_on_each_variant_except_ctrl_chars!(self, cursor_move, "Expected a canonical Stick that is not a control character.")
+        match_func_on_each_variant_except_ctrl_chars!(self, cursor_move, "Expected a canonical Stick that is not a control character or decoration.")
     }
 
     pub fn is_leaf(&self) -> bool {
         match_func_on_each_variant!(self, is_leaf)
     }
+
+    pub fn is_decoration(&self) -> bool {
+        match_func_on_each_variant!(self, is_decoration)
+    }
+
+    //The same stick seen in a mirror, i.e. with left and right swapped.
+    pub fn mirrored(&self) -> Stick {
+        match self {
+            Stick::UpLeftBranch => Stick::UpRightBranch,
+            Stick::UpRightBranch => Stick::UpLeftBranch,
+
+            Stick::LeftLeaf => Stick::RightLeaf,
+            Stick::RightLeaf => Stick::LeftLeaf,
+
+            Stick::UpLeftLeaf => Stick::UpRightLeaf,
+            Stick::UpRightLeaf => Stick::UpLeftLeaf,
+            Stick::DownLeftLeaf => Stick::DownRightLeaf,
+            Stick::DownRightLeaf => Stick::DownLeftLeaf,
+
+            _ => *self,
+        }
+    }
+
+    //The branch or leaf that moves the cursor by cursor_move.
+    pub fn from_cursor_move(cursor_move: Point, is_leaf: bool) -> Result<Stick, &'static str> {
+        match (cursor_move.x, cursor_move.y, is_leaf) {
+            (0, 1, false) => Ok(Stick::UpBranch),
+            (-1, 1, false) => Ok(Stick::UpLeftBranch),
+            (1, 1, false) => Ok(Stick::UpRightBranch),
+
+            (0, 1, true) => Ok(Stick::UpLeaf),
+            (-1, 0, true) => Ok(Stick::LeftLeaf),
+            (1, 0, true) => Ok(Stick::RightLeaf),
+            (0, -1, true) => Ok(Stick::DownLeaf),
+
+            (-1, 1, true) => Ok(Stick::UpLeftLeaf),
+            (1, 1, true) => Ok(Stick::UpRightLeaf),
+            (-1, -1, true) => Ok(Stick::DownLeftLeaf),
+            (1, -1, true) => Ok(Stick::DownRightLeaf),
+
+            _ => Err("No stick moves the cursor like that."),
+        }
+    }
+}
+
+//A tree string made of canonical sticks, a Stick at a time.
+pub fn to_sticks(tree_string: &str) -> Result<Vec<Stick>, &'static str> {
+    tree_string.chars().map(Stick::try_from).collect()
+}
+
+//Flips a tree string made of canonical sticks left to right.
+pub fn mirror(tree_string: &str) -> Result<String, &'static str> {
+    Ok(to_sticks(tree_string)?.iter().map(|stick| stick.mirrored().to_char()).collect())
 }
 
 //Perhaps this isn't necessary, but whatever.
@@ -116,6 +177,10 @@ const BRANCH_RETURN_C: char = 'r';
 const LEAF_SPAWN_C: char = 'o';
 const LEAF_RETURN_C: char = 'l';
 
+const FRUIT_C: char = '@';
+const FLOWER_C: char = '*';
+const BLOSSOM_C: char = '%';
+
 impl TryFrom<char> for Stick {
     type Error = &'static str;
 
@@ -141,6 +206,10 @@ impl TryFrom<char> for Stick {
             LEAF_SPAWN_C => Ok(Stick::LeafSpawn),
             LEAF_RETURN_C => Ok(Stick::LeafReturn),
 
+            FRUIT_C => Ok(Stick::Fruit),
+            FLOWER_C => Ok(Stick::Flower),
+            BLOSSOM_C => Ok(Stick::Blossom),
+
             _ => Err("Expected one of the canonical Sticks."),
         }
     }
@@ -155,6 +224,7 @@ pub trait StickCanonical {
     fn to_char() -> char;
     fn is_control_char() -> bool;
     fn is_leaf() -> bool;
+    fn is_decoration() -> bool;
 }
 
 macro_rules! sticknonctrl {
@@ -181,6 +251,10 @@ macro_rules! sticknonctrl {
             fn is_leaf() -> bool {
                 $is_leaf
             }
+
+            fn is_decoration() -> bool {
+                false
+            }
         }
 
         impl fmt::Display for $name {
@@ -241,6 +315,10 @@ macro_rules! stickctrl {
             fn is_leaf() -> bool {
                 false
             }
+
+            fn is_decoration() -> bool {
+                false
+            }
         }
 
         impl fmt::Display for $name {
@@ -271,3 +349,180 @@ pub struct LeafReturn;
 
 stickctrl!(LeafSpawn, LEAF_SPAWN_C);
 stickctrl!(LeafReturn, LEAF_RETURN_C);
+
+macro_rules! stickdecor {
+    ($name: ty, $c: expr) => {
+        impl StickCanonical for $name {
+            fn is_same_char(c: char) -> bool {
+                $c == c
+            }
+
+            fn to_char() -> char {
+                $c
+            }
+
+            fn is_control_char() -> bool {
+                false
+            }
+
+            fn is_leaf() -> bool {
+                false
+            }
+
+            fn is_decoration() -> bool {
+                true
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", $c)
+            }
+        }
+    }
+}
+
+//Sticks that hang off of the tip of a leaf.
+//
+//They don't move the cursor, and don't change the flow of the tree either,
+//they just replace the leaf the cursor is sitting on with their own character.
+//Outside of a leaflet there's no leaf to hang off of, so they're only valid there.
+pub struct Fruit;
+pub struct Flower;
+pub struct Blossom;
+
+stickdecor!(Fruit, FRUIT_C);
+stickdecor!(Flower, FLOWER_C);
+stickdecor!(Blossom, BLOSSOM_C);
+
+//The tree string language, through the ages.
+//
+//A tree string can start with a header recording the version of the language it was written in,
+//e.g. "v2:". Strings without one predate headers entirely, so they're v1.
+//When the language changes, add a version to LANGUAGE_VERSIONS with a migration from the version
+//before it, so stored trees keep rendering the way they did when they were generated.
+pub struct LanguageVersion {
+    pub version: u32,
+    pub summary: &'static str,
+    //Every stick character that is valid in this version.
+    pub alphabet: &'static str,
+    //Whether repetition counts and macros are expanded before reading the sticks.
+    pub has_macros: bool,
+    //Upgrades a tree string written in the previous version to this one.
+    migrate_from_previous: fn(&str) -> Result<String, &'static str>,
+}
+
+pub const CURRENT_LANGUAGE_VERSION: u32 = 3;
+
+//Oldest first.
+pub const LANGUAGE_VERSIONS: &[LanguageVersion] = &[
+    LanguageVersion {
+        version: 1,
+        summary: "Branches, leaves, and the branch and leaf control characters.",
+        alphabet: "|\\/^<>vtubmyrol",
+        has_macros: false,
+        migrate_from_previous: unchanged,
+    },
+    LanguageVersion {
+        version: 2,
+        summary: "Adds fruit, flower and blossom decorations.",
+        alphabet: "|\\/^<>vtubmyrol@*%",
+        has_macros: false,
+        migrate_from_previous: unchanged,
+    },
+    LanguageVersion {
+        version: 3,
+        summary: "Adds repetition counts, e.g. \"12|\", and macros, e.g. \"[A=o^<l]yA|A\".",
+        alphabet: "|\\/^<>vtubmyrol@*%",
+        has_macros: true,
+        migrate_from_previous: unchanged,
+    },
+];
+
+//For versions that only add sticks, everything old still means the same thing.
+fn unchanged(tree_string: &str) -> Result<String, &'static str> {
+    Ok(tree_string.to_string())
+}
+
+pub fn language_version(version: u32) -> Option<&'static LanguageVersion> {
+    LANGUAGE_VERSIONS.iter().find(|v| v.version == version)
+}
+
+//Upgrade a tree string written in from_version to CURRENT_LANGUAGE_VERSION.
+pub fn migrate(tree_string: &str, from_version: u32) -> Result<String, &'static str> {
+    let from = match LANGUAGE_VERSIONS.iter().position(|v| v.version == from_version) {
+        Some(i) => i,
+        None if from_version > CURRENT_LANGUAGE_VERSION => return Err("Tree string is from a newer language version than this fvtree knows about."),
+        None => return Err("Tree string has an unknown language version."),
+    };
+
+    if !tree_string.chars().all(|c| LANGUAGE_VERSIONS[from].alphabet.contains(c)) {
+        return Err("Tree string contains a stick that doesn't exist in its language version.");
+    }
+
+    let mut migrated = tree_string.to_string();
+    for version in &LANGUAGE_VERSIONS[from + 1..] {
+        migrated = (version.migrate_from_previous)(&migrated)?;
+    }
+
+    Ok(migrated)
+}
+
+//Every cell a branch or leaf lands on, in order, following the cursor from start the way
+//Fvtree::build does. The tree string must be made of canonical sticks.
+pub fn cells_landed_on(tree_string: &str, start: Point) -> Vec<(i32, i32)> {
+    let mut cursor = start;
+    let mut branch_points = Vec::new();
+    let mut leaf_spawn_point = cursor;
+    let mut cells = Vec::new();
+
+    for c in tree_string.chars() {
+        match Stick::try_from(c).expect("Tree strings are made of canonical sticks.") {
+            Stick::BranchIndicator => branch_points.push(cursor),
+            Stick::BranchReturn => cursor = branch_points.pop().unwrap_or(start),
+            Stick::LeafSpawn => leaf_spawn_point = cursor,
+            Stick::LeafReturn => cursor = leaf_spawn_point,
+            stick if stick.is_decoration() => (),
+            stick => {
+                cursor += stick.cursor_move().expect("Everything else is a branch or a leaf.");
+                cells.push((cursor.x, cursor.y));
+            },
+        }
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod sticks_tests {
+    use super::*;
+
+    #[test]
+    fn current_version_is_last() {
+        assert_eq!(LANGUAGE_VERSIONS.last().unwrap().version, CURRENT_LANGUAGE_VERSION);
+    }
+
+    #[test]
+    fn alphabets_are_sticks() {
+        for version in LANGUAGE_VERSIONS {
+            for c in version.alphabet.chars() {
+                assert!(Stick::try_from(c).is_ok(), "'{}' in v{} is not a Stick.", c, version.version);
+            }
+        }
+    }
+
+    #[test]
+    fn migrate_v1() {
+        assert_eq!(migrate("||yo^lr", 1), Ok("||yo^lr".to_string()));
+        assert!(migrate("||yo^@lr", 1).is_err());
+        assert!(migrate("||", CURRENT_LANGUAGE_VERSION + 1).is_err());
+        assert!(migrate("||", 0).is_err());
+    }
+
+    #[test]
+    fn mirror_twice() {
+        assert_eq!(mirror("|\\y/o<t@lbr"), Ok("|/y\\o>u@lmr".to_string()));
+        let alphabet = LANGUAGE_VERSIONS.last().unwrap().alphabet;
+        assert_eq!(mirror(&mirror(alphabet).unwrap()), Ok(alphabet.to_string()));
+    }
+}
@@ -0,0 +1,449 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+
+use common::Point;
+
+use crate::sticks::Stick;
+use crate::canonical;
+use crate::{Fvtree, FvtreeString, Tracing};
+
+//Working back from a picture of a tree to a tree string, the other way from Fvtree::build.
+//
+//Starting from the root, an O over /|\, branches are followed up through cells with the stick
+//that would've drawn them, | straight up, / up and to the right and \ up and to the left. A Y or
+//O could be over any stick, since they're drawn over whatever was there. Where a branch goes more
+//than one way, or there's a Y, the other ways are branches off of it.
+//Leaves only grow out of an O, so leaves are followed out from every O, in any direction.
+//
+//Leaves are drawn over whatever's there, so a branch can go on under a leaf, as long as there's
+//more of it past the leaf. Leaves that can't be reached from any O, since it's been drawn over
+//too, grow from a new O on a branch next to them instead, as long as that O ends up drawn over
+//as well.
+//
+//Pictures are rarely drawn exactly how Fvtree::build would draw them, so the tree string's the
+//closest that can be found, and whatever couldn't be traced back to the root is left out.
+//Which is closest is down to building the tree string and comparing it with the picture, cell by
+//cell, so every cell that comes out differently can be told apart too.
+
+const BRANCH_POINT_GLYPH: char = 'Y';
+const LEAF_SPAWN_GLYPH: char = 'O';
+//Leaves in any season.
+const LEAF_GLYPHS: &str = "\"'%@*";
+
+const BRANCH_MOVES: [(i32, i32); 3] = [(-1, 1), (0, 1), (1, 1)];
+const LEAF_MOVES: [(i32, i32); 8] = [(-1, 1), (0, 1), (1, 1), (-1, 0), (1, 0), (-1, -1), (0, -1), (1, -1)];
+
+//The cells of a block of text, with y going up like it does on a Canvas.
+pub fn cells_of_art(art: &str) -> HashMap<(i32, i32), char> {
+    let mut cells = HashMap::new();
+
+    for (row, line) in art.lines().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            if !c.is_whitespace() {
+                cells.insert((x as i32, -(row as i32)), c);
+            }
+        }
+    }
+
+    cells
+}
+
+pub fn cells_of_canvas(canvas: &text_canvas::Canvas) -> HashMap<(i32, i32), char> {
+    canvas.cells().into_iter().map(|(point, c)| ((point.x, point.y), c)).collect()
+}
+
+//The cell over /|\, the lowest, then leftmost, if there's more than one, and one with an O on it
+//before one without. Leaves can be drawn over the root's O, but nothing's drawn lower than its
+///|\ other than leaves.
+fn find_root(cells: &HashMap<(i32, i32), char>) -> Option<(i32, i32)> {
+    cells.iter()
+        .filter(|&(&(x, y), &c)| {
+            c == '|'
+                && cells.get(&(x - 1, y)) == Some(&'/')
+                && cells.get(&(x + 1, y)) == Some(&'\\')
+        })
+        .map(|(&(x, y), _)| (x, y + 1))
+        .min_by_key(|&(x, y)| (y, cells.get(&(x, y)) != Some(&LEAF_SPAWN_GLYPH), x))
+}
+
+struct Tracer {
+    cells: HashMap<(i32, i32), char>,
+    //Every cell that's drawn by the tree string so far.
+    traced: HashSet<(i32, i32)>,
+    //Every cell a branch has gone through, including under leaves, in the order they were.
+    branch_cells: Vec<(i32, i32)>,
+    //Every cell a branch is going through, including under leaves, so no other branch does.
+    on_a_branch: HashSet<(i32, i32)>,
+    //Where to put a new O, for leaves with no O of their own.
+    new_leaf_spawns: HashSet<(i32, i32)>,
+    //Whether branches can go on under leaves. They can, but where they can't, the leaves might be
+    //in the way of a branch that goes around them instead.
+    under_leaves: bool,
+    //Whether a branch can split off anywhere it could go more than one way. Fvtree::build only
+    //splits them at a Y, which an O or a leaf can be drawn over, so otherwise that's the only
+    //place they do, and everywhere else they go the first way they can.
+    splits_anywhere: bool,
+    tree_string: String,
+}
+
+impl Tracer {
+    fn at(&self, cell: (i32, i32)) -> Option<char> {
+        self.cells.get(&cell).copied()
+    }
+
+    fn is_leaf(&self, cell: (i32, i32)) -> bool {
+        self.at(cell).is_some_and(|c| LEAF_GLYPHS.contains(c))
+    }
+
+    //Whether a branch going (x, y) from cell could've drawn what's there, if it's not on a branch
+    //already.
+    fn branch_goes(&self, cell: (i32, i32), (x, y): (i32, i32)) -> bool {
+        let next = (cell.0 + x, cell.1 + y);
+        let stick = Stick::from_cursor_move(Point {x, y}, false).expect("Branches go up every way.");
+
+        !self.on_a_branch.contains(&next)
+            && self.at(next).is_some_and(|c| c == stick.to_char() || c == BRANCH_POINT_GLYPH || c == LEAF_SPAWN_GLYPH)
+    }
+
+    //Whether a branch going (x, y) from cell could be under a leaf, with more of it past the leaf.
+    fn branch_goes_under(&self, cell: (i32, i32), (x, y): (i32, i32)) -> bool {
+        let next = (cell.0 + x, cell.1 + y);
+
+        self.under_leaves
+            && !self.on_a_branch.contains(&next)
+            && self.is_leaf(next)
+            && BRANCH_MOVES.iter().any(|&way| self.branch_goes(next, way))
+    }
+
+    //Puts down a leaf onto cell, and whatever hangs off of it.
+    fn put_leaf(&mut self, from: (i32, i32), to: (i32, i32)) {
+        let leaf = Stick::from_cursor_move(Point {x: to.0 - from.0, y: to.1 - from.1}, true).expect("Leaves go every way.");
+        self.tree_string.push(leaf.to_char());
+        self.traced.insert(to);
+
+        if let Some(decoration) = self.at(to).and_then(|c| Stick::try_from(c).ok()).filter(|stick| stick.is_decoration()) {
+            self.tree_string.push(decoration.to_char());
+        }
+    }
+
+    //Every leaf out from the O at spawn, as many times out and back as it takes.
+    fn trace_leaflet(&mut self, spawn: (i32, i32)) {
+        loop {
+            //The nearest leaf that's yet to be traced, through the ones that have been.
+            let mut came_from = HashMap::new();
+            let mut queue = VecDeque::from([spawn]);
+            let mut nearest = None;
+            while let Some(cell) = queue.pop_front() {
+                if cell != spawn && !self.traced.contains(&cell) {
+                    nearest = Some(cell);
+                    break;
+                }
+                for (x, y) in LEAF_MOVES {
+                    let next = (cell.0 + x, cell.1 + y);
+                    if next != spawn && self.is_leaf(next) && !came_from.contains_key(&next) {
+                        came_from.insert(next, cell);
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            let nearest = match nearest {
+                Some(nearest) => nearest,
+                None => break,
+            };
+
+            let mut path = vec![nearest];
+            while let Some(&cell) = came_from.get(path.last().unwrap()) {
+                path.push(cell);
+            }
+            path.reverse();
+            for step in path.windows(2) {
+                self.put_leaf(step[0], step[1]);
+            }
+
+            //Then on as far as the leaves go.
+            let mut cell = nearest;
+            while let Some(next) = LEAF_MOVES.iter()
+                .map(|(x, y)| (cell.0 + x, cell.1 + y))
+                .find(|&next| self.is_leaf(next) && !self.traced.contains(&next)) {
+                self.put_leaf(cell, next);
+                cell = next;
+            }
+
+            self.tree_string.push(Stick::LeafReturn.to_char());
+        }
+
+        //A new O where there's a leaf gets the leaf drawn back over it, out to the leaf next to it
+        //and back.
+        if self.is_leaf(spawn) {
+            if let Some(next) = LEAF_MOVES.iter().map(|(x, y)| (spawn.0 + x, spawn.1 + y)).find(|&next| self.is_leaf(next)) {
+                self.put_leaf(spawn, next);
+                self.put_leaf(next, spawn);
+                self.tree_string.push(Stick::LeafReturn.to_char());
+            }
+        }
+    }
+
+    //Everything on the branch from cell on, with the cursor at cell.
+    fn trace_branch(&mut self, cell: (i32, i32), is_root: bool) {
+        let c = self.at(cell);
+        let mut ways: Vec<(i32, i32)> = BRANCH_MOVES.iter()
+            .copied()
+            .filter(|&way| self.branch_goes(cell, way) || self.branch_goes_under(cell, way))
+            .collect();
+        let is_knot = c == Some(BRANCH_POINT_GLYPH) || c == Some(LEAF_SPAWN_GLYPH) || self.is_leaf(cell);
+        if !self.splits_anywhere && !is_knot {
+            ways.truncate(1);
+        }
+        //So no branch goes off the way another's already going. What's under a leaf is left for
+        //the leaf to be drawn over.
+        for &(x, y) in ways.iter() {
+            let next = (cell.0 + x, cell.1 + y);
+            self.on_a_branch.insert(next);
+            if !self.is_leaf(next) {
+                self.traced.insert(next);
+            }
+        }
+        self.branch_cells.push(cell);
+
+        let branch = |(x, y): (i32, i32)| Stick::from_cursor_move(Point {x, y}, false).expect("Branches go up every way.").to_char();
+
+        //A new O goes before anything else, so whatever's drawn there is drawn over it.
+        let is_new_leaf_spawn = c != Some(LEAF_SPAWN_GLYPH) && self.new_leaf_spawns.contains(&cell);
+        if is_new_leaf_spawn {
+            self.tree_string.push(Stick::LeafSpawn.to_char());
+            self.trace_leaflet(cell);
+        }
+
+        //A Y needs a BranchIndicator, even without a branch off of it.
+        if c == Some(BRANCH_POINT_GLYPH) && ways.len() <= 1 {
+            self.tree_string.push(Stick::BranchIndicator.to_char());
+            self.tree_string.push(Stick::BranchReturn.to_char());
+        }
+        if let Some((_, others)) = ways.split_last() {
+            for &way in others {
+                self.tree_string.push(Stick::BranchIndicator.to_char());
+                self.tree_string.push(branch(way));
+                self.trace_branch((cell.0 + way.0, cell.1 + way.1), false);
+                self.tree_string.push(Stick::BranchReturn.to_char());
+            }
+        }
+        //After the branches, so the O's drawn over their Ys. The root's already drawn,
+        //unless there's a Y over it.
+        if c == Some(LEAF_SPAWN_GLYPH) {
+            let has_leaves = LEAF_MOVES.iter().any(|(x, y)| self.is_leaf((cell.0 + x, cell.1 + y)));
+            if !is_root || has_leaves || ways.len() > 1 {
+                self.tree_string.push(Stick::LeafSpawn.to_char());
+                self.trace_leaflet(cell);
+            }
+        }
+        if let Some(&last) = ways.last() {
+            self.tree_string.push(branch(last));
+            self.trace_branch((cell.0 + last.0, cell.1 + last.1), false);
+        }
+    }
+}
+
+//Traces the tree in cells, with the root at (0, 0), from the root up.
+fn trace_from_root(cells: &HashMap<(i32, i32), char>, new_leaf_spawns: HashSet<(i32, i32)>, (under_leaves, splits_anywhere): (bool, bool)) -> Tracer {
+    let mut tracer = Tracer {
+        cells: cells.clone(),
+        traced: HashSet::new(),
+        branch_cells: Vec::new(),
+        on_a_branch: HashSet::new(),
+        new_leaf_spawns,
+        under_leaves,
+        splits_anywhere,
+        tree_string: String::new(),
+    };
+    for cell in [(0, 0), (-1, -1), (0, -1), (1, -1)] {
+        tracer.traced.insert(cell);
+        tracer.on_a_branch.insert(cell);
+    }
+    tracer.trace_branch((0, 0), true);
+
+    tracer
+}
+
+//What tracer traced, built, with every cell where the tree it builds isn't what's in cells.
+fn build_traced(cells: &HashMap<(i32, i32), char>, tracer: &Tracer) -> Result<Tracing, &'static str> {
+    let fvtree_string = FvtreeString{tree_string: canonical::canonicalize(&tracer.tree_string)?};
+    let built = cells_of_canvas(&Fvtree::build(&fvtree_string)?.canvas);
+
+    let mut differences: Vec<(Point, Option<char>, Option<char>)> = cells.keys()
+        .chain(built.keys().filter(|cell| !cells.contains_key(cell)))
+        .filter(|cell| cells.get(cell) != built.get(cell))
+        .map(|&(x, y)| (Point {x, y}, cells.get(&(x, y)).copied(), built.get(&(x, y)).copied()))
+        .collect();
+    differences.sort_by_key(|(point, _, _)| (-point.y, point.x));
+
+    Ok(Tracing {fvtree_string, differences})
+}
+
+//Where a new O could go for leaves that tracer couldn't reach from any O, on a branch next to
+//them, for each bunch of leaves. Where there's a leaf on the branch, first, since the O might
+//have been drawn over, then anywhere but a Y or O, then on one.
+fn new_leaf_spawns(tracer: &Tracer) -> Vec<Vec<(i32, i32)>> {
+    let mut spawns = Vec::new();
+    let mut reached = HashSet::new();
+    let mut lost_leaves: Vec<(i32, i32)> = tracer.cells.keys()
+        .copied()
+        .filter(|&cell| tracer.is_leaf(cell) && !tracer.traced.contains(&cell))
+        .collect();
+    lost_leaves.sort_by_key(|&(x, y)| (-y, x));
+
+    for leaf in lost_leaves {
+        if !reached.insert(leaf) {
+            continue;
+        }
+
+        //Every leaf as far as they go from this one.
+        let mut bunch = vec![leaf];
+        let mut queue = vec![leaf];
+        while let Some(cell) = queue.pop() {
+            for (x, y) in LEAF_MOVES {
+                let next = (cell.0 + x, cell.1 + y);
+                if tracer.is_leaf(next) && reached.insert(next) {
+                    bunch.push(next);
+                    queue.push(next);
+                }
+            }
+        }
+
+        let next_to_bunch = |cell: (i32, i32)| bunch.iter().any(|leaf| LEAF_MOVES.iter().any(|(x, y)| (cell.0 + x, cell.1 + y) == *leaf));
+        let mut next_to: Vec<(i32, i32)> = tracer.branch_cells.iter()
+            .copied()
+            .filter(|&cell| next_to_bunch(cell) || bunch.contains(&cell))
+            .collect();
+        next_to.sort_by_key(|&cell| (!tracer.is_leaf(cell), tracer.at(cell).is_some_and(|c| c == BRANCH_POINT_GLYPH || c == LEAF_SPAWN_GLYPH)));
+        next_to.dedup();
+        spawns.push(next_to);
+    }
+
+    spawns
+}
+
+//Whether after gets more of cells right than before, without getting anything wrong that before
+//got right.
+fn is_closer(after: &Tracing, before: &Tracing) -> bool {
+    after.differences.len() < before.differences.len()
+        && after.differences.iter().all(|(point, _, _)| before.differences.iter().any(|(other, _, _)| other == point))
+}
+
+//The tree string for the tree in cells, and every cell where the tree it builds, in summer, isn't
+//what's in cells, with the root at (0, 0). Errors if there's no root.
+pub fn trace(cells: &HashMap<(i32, i32), char>) -> Result<Tracing, &'static str> {
+    let (root_x, root_y) = find_root(cells).ok_or("Couldn't find the root, the /|\\ under the tree.")?;
+    let cells: HashMap<(i32, i32), char> = cells.iter().map(|(&(x, y), &c)| ((x - root_x, y - root_y), c)).collect();
+
+    let mut closest: Option<Tracing> = None;
+    //Every way of tracing it, (under_leaves, splits_anywhere), see Tracer.
+    for way in [(true, true), (false, true), (true, false), (false, false)] {
+        //First with just the Os that are there.
+        let tracer = trace_from_root(&cells, HashSet::new(), way);
+        let mut tracing = build_traced(&cells, &tracer)?;
+
+        //Then with a new O for each bunch of leaves that couldn't be reached, as long as it
+        //doesn't draw over anything that was right.
+        let mut spawns = HashSet::new();
+        for bunch in new_leaf_spawns(&tracer) {
+            for spawn in bunch {
+                spawns.insert(spawn);
+                let with_spawn = build_traced(&cells, &trace_from_root(&cells, spawns.clone(), way))?;
+                if is_closer(&with_spawn, &tracing) {
+                    tracing = with_spawn;
+                    break;
+                }
+                spawns.remove(&spawn);
+            }
+        }
+
+        if closest.as_ref().is_none_or(|closest| tracing.differences.len() < closest.differences.len()) {
+            closest = Some(tracing);
+        }
+    }
+
+    Ok(closest.expect("There's been at least one tracing."))
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::tree_gen_recursive;
+    use crate::confstats::Stats;
+    use crate::{Fvtree, FvtreeString, Config};
+
+    #[test]
+    fn finding_the_root() {
+        let cells = cells_of_art(" O\n O\n/|\\\n");
+        assert_eq!(find_root(&cells), Some((1, -1)));
+        //A leaf's been drawn over it.
+        assert_eq!(find_root(&cells_of_art(" \"\n/|\\\n")), Some((1, 0)));
+        assert_eq!(find_root(&cells_of_art(" O\n/ \\\n")), None);
+    }
+
+    #[test]
+    fn tracing() {
+        let art = concat!(
+            "\"O \n",
+            " Y| \n",
+            " |/ \n",
+            " O  \n",
+            "/|\\ \n",
+        );
+        let tracing = trace(&cells_of_art(art)).unwrap();
+
+        //Two ways from the root, so the O goes back over the Y.
+        assert_eq!(tracing.fvtree_string.tree_string, "y||yr|o<ro/|");
+        assert!(tracing.differences.is_empty());
+    }
+
+    #[test]
+    fn untraceable_cells() {
+        let art = concat!(
+            "  #  \n",
+            "  | \"\n",
+            "  O  \n",
+            " /|\\ \n",
+        );
+        let tracing = trace(&cells_of_art(art)).unwrap();
+
+        assert_eq!(tracing.fvtree_string.tree_string, "|");
+        assert_eq!(tracing.differences, vec![(Point {x: 0, y: 2}, Some('#'), None), (Point {x: 2, y: 1}, Some('"'), None)]);
+        assert!(trace(&cells_of_art("||")).is_err());
+    }
+
+    #[test]
+    fn tracing_rendered_trees() {
+        //With nothing drawn over anything else, not even the root, nothing's lost, so rendered
+        //trees come back exactly.
+        let mut conf = Config::new_fruit_tree();
+        conf.t.avoid_collisions = true;
+        conf.t.min_trunk_height = 0;
+        conf.t.min_sticks = 0;
+        conf.b.min_sticks = 0;
+        conf.b.allow_dead_branches = true;
+        conf.b.min_leaves_in_leaflet = 0;
+        conf.b.min_leaflets = 0;
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let fvtree_string = FvtreeString{tree_string: tree_gen_recursive::gen(&mut rng, &mut Stats::new(), &conf)};
+            let art = Fvtree::build(&fvtree_string).unwrap().to_string();
+            let tracing = FvtreeString::trace(&art).unwrap();
+            assert_eq!(Fvtree::build(&tracing.fvtree_string).unwrap().to_string(), art, "{}", fvtree_string);
+            assert!(tracing.differences.is_empty());
+        }
+
+        //Leaves drawn over branches lose some of the tree, but whatever doesn't come back is said so.
+        for _ in 0..20 {
+            let art = Fvtree::build(&FvtreeString::new_recursive_from_config(&Config::new_fruit_tree())).unwrap().to_string();
+            let tracing = FvtreeString::trace(&art).unwrap();
+            let traced = Fvtree::build(&tracing.fvtree_string).unwrap().to_string();
+            assert_eq!(traced == art, tracing.differences.is_empty());
+        }
+
+        assert!(FvtreeString::trace("|||\n").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use std::convert::TryFrom;
+
+use common::Point;
+
+use crate::sticks::Stick;
+
+//Reshaping tree strings, stick by stick, so what comes out is still a tree string.
+//For flipping the way a tree faces, see sticks::mirror.
+
+//Twice the size, every branch and leaf put down twice. Decorations aren't made any bigger.
+pub fn scale_up(sticks: &[Stick]) -> String {
+    let mut output = String::new();
+
+    for stick in sticks {
+        output.push(stick.to_char());
+        if !stick.is_control_char() && !stick.is_decoration() {
+            output.push(stick.to_char());
+        }
+    }
+
+    output
+}
+
+//Sheared over to the right, so every cell is moved percent percent of its height further right,
+//or left if percent is negative. Sticks only go one cell sideways, so anything leaning more than
+//100 percent either way leans 100 percent, and sticks already leaning as far as they go that way
+//stay put.
+pub fn lean(tree_string: &str, percent: i32) -> Result<String, &'static str> {
+    let percent = percent.clamp(-100, 100);
+    //How far a cell at height y is moved, rounded to the nearest cell.
+    let shift = |y: i32| (2 * y * percent + 100).div_euclid(200);
+    let mut cursor = Point {x: 0, y: 0};
+    let mut leaf_spawn_point = cursor;
+    let mut branch_points: Vec<Point> = Vec::new();
+    let mut output = String::new();
+
+    for c in tree_string.chars() {
+        let stick = Stick::try_from(c)?;
+
+        match stick {
+            Stick::BranchIndicator => branch_points.push(cursor),
+            Stick::BranchReturn => cursor = branch_points.pop().ok_or("Found a BranchReturn but not a corresponding BranchIndicator.")?,
+            Stick::LeafSpawn => leaf_spawn_point = cursor,
+            Stick::LeafReturn => cursor = leaf_spawn_point,
+            stick if stick.is_decoration() => (),
+            stick => {
+                let cursor_move = stick.cursor_move()?;
+                let landing = cursor + cursor_move;
+                let x = (cursor_move.x + shift(landing.y) - shift(cursor.y)).clamp(-1, 1);
+                cursor = landing;
+
+                output.push(Stick::from_cursor_move(Point {x, y: cursor_move.y}, stick.is_leaf())?.to_char());
+                continue;
+            },
+        }
+
+        output.push(c);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod transform_tests {
+    use super::*;
+    use crate::sticks::to_sticks;
+    use crate::{Fvtree, FvtreeString, Config};
+
+    #[test]
+    fn scaling_up() {
+        assert_eq!(scale_up(&to_sticks("|y/o^@l<r|").unwrap()), "||y//o^^@l<<r||");
+    }
+
+    #[test]
+    fn leaning() {
+        assert_eq!(lean("||||", 100), Ok("////".to_string()));
+        assert_eq!(lean("||||", -50), Ok("|\\|\\".to_string()));
+        assert_eq!(lean("||||", 0), Ok("||||".to_string()));
+        //Already leaning that way, and leaves that don't go up or down.
+        assert_eq!(lean("\\/o<>l^", 100), Ok("|/o<>lu".to_string()));
+        //Back down the way it went, once the branch is done.
+        assert_eq!(lean("|y|r|", 100), Ok("/y/r/".to_string()));
+    }
+
+    #[test]
+    fn transforms() {
+        for _ in 0..20 {
+            let fvtree_string = FvtreeString::new_recursive_from_config(&Config::new_fruit_tree());
+
+            //Mirroring the tree string is the same as flipping the rendered tree.
+            let mirrored = fvtree_string.mirror();
+            let mut flipped = Fvtree::build(&fvtree_string).unwrap();
+            flipped.flip_horizontal();
+            assert_eq!(Fvtree::build(&mirrored).unwrap().canvas.cells(), flipped.canvas.cells());
+            assert_eq!(mirrored.mirror().tree_string(), fvtree_string.tree_string());
+
+            let scaled_up = fvtree_string.scale_up();
+            assert!(Fvtree::build(&scaled_up).is_ok());
+            assert_eq!(fvtree_string.tree_string().matches('y').count(), scaled_up.tree_string().matches('y').count());
+
+            for percent in [-100, -30, 0, 30, 100] {
+                let leaning = fvtree_string.lean(percent).unwrap();
+                assert_eq!(leaning.tree_string().len(), fvtree_string.tree_string().len());
+                assert!(Fvtree::build(&leaning).is_ok());
+            }
+        }
+    }
+}
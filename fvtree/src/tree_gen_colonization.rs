@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use common::Point;
+
+use crate::sticks::{Stick, StickCanonical};
+use crate::sticks::{BranchIndicator, BranchReturn};
+use crate::sticks::{LeafSpawn, LeafReturn};
+
+//Space colonization, on the canvas grid.
+//
+//Scatter attraction points through the crown, then grow the tree a stick at a time towards
+//whichever points are nearest each of its tips, until every point has been reached or nothing
+//can grow anymore. Every stick knows where it lands before the tree string is written, so no
+//two sticks land on the same cell, which is what makes Fvtree::build skip sticks otherwise.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CrownShape {
+    Ellipse,
+    //Wide at the bottom, a point at the top.
+    Cone,
+    //A flat bottomed dome.
+    Umbrella,
+}
+
+impl FromStr for CrownShape {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<CrownShape, &'static str> {
+        match s {
+            "ellipse" => Ok(CrownShape::Ellipse),
+            "cone" => Ok(CrownShape::Cone),
+            "umbrella" => Ok(CrownShape::Umbrella),
+            _ => Err("Crown shapes are ellipse, cone or umbrella."),
+        }
+    }
+}
+
+//Sizes are in canvas cells.
+pub struct ColonizationConfig {
+    pub crown: CrownShape,
+    pub trunk_height: usize,
+    //Half of the crown's width.
+    pub crown_radius: usize,
+    pub crown_height: usize,
+    pub attraction_points: usize,
+    //Tips only grow towards points at most this far away.
+    pub influence_radius: usize,
+    //Points this close to the tree have been reached, and stop attracting it.
+    pub kill_distance: usize,
+    pub max_leaves_per_tip: usize,
+    pub max_sticks: usize,
+}
+
+impl ColonizationConfig {
+    pub fn new() -> ColonizationConfig {
+        ColonizationConfig {
+            crown: CrownShape::Ellipse,
+            trunk_height: 4,
+            crown_radius: 12,
+            crown_height: 12,
+            attraction_points: 60,
+            influence_radius: 10,
+            kill_distance: 2,
+            max_leaves_per_tip: 3,
+            max_sticks: 400,
+        }
+    }
+}
+
+impl Default for ColonizationConfig {
+    fn default() -> ColonizationConfig {
+        ColonizationConfig::new()
+    }
+}
+
+//Branch sticks only ever grow upwards.
+const GROWTH_STICKS: [Stick; 3] = [Stick::UpBranch, Stick::UpLeftBranch, Stick::UpRightBranch];
+const LEAF_STICKS: [Stick; 8] = [
+    Stick::UpLeaf, Stick::LeftLeaf, Stick::RightLeaf, Stick::DownLeaf,
+    Stick::UpLeftLeaf, Stick::UpRightLeaf, Stick::DownLeftLeaf, Stick::DownRightLeaf,
+];
+
+struct Node {
+    point: Point,
+    //The stick that got here from the parent node. The root doesn't have one.
+    stick: Option<Stick>,
+    children: Vec<usize>,
+}
+
+fn cell(point: Point) -> (i32, i32) {
+    (point.x, point.y)
+}
+
+fn distance_squared(a: Point, b: Point) -> i64 {
+    let (dx, dy) = ((a.x - b.x) as i64, (a.y - b.y) as i64);
+    dx * dx + dy * dy
+}
+
+fn in_crown(conf: &ColonizationConfig, point: Point) -> bool {
+    let base = conf.trunk_height as f64;
+    let (radius, height) = (conf.crown_radius.max(1) as f64, conf.crown_height.max(1) as f64);
+    let (x, y) = (point.x as f64, point.y as f64 - base);
+
+    if y < 0.0 || y > height {
+        return false;
+    }
+
+    match conf.crown {
+        CrownShape::Ellipse => {
+            let (dx, dy) = (x / radius, (y - height / 2.0) / (height / 2.0));
+            dx * dx + dy * dy <= 1.0
+        },
+        CrownShape::Cone => x.abs() <= radius * (1.0 - y / height),
+        CrownShape::Umbrella => {
+            let (dx, dy) = (x / radius, y / height);
+            dx * dx + dy * dy <= 1.0
+        },
+    }
+}
+
+fn scatter_attraction_points(rng: &mut rand::rngs::ThreadRng, conf: &ColonizationConfig) -> Vec<Point> {
+    let base = conf.trunk_height as i32;
+    let (radius, height) = (conf.crown_radius as i32, conf.crown_height as i32);
+
+    let mut cells: Vec<Point> = (base..=base + height)
+        .flat_map(|y| (-radius..=radius).map(move |x| Point {x, y}))
+        .filter(|point| in_crown(conf, *point))
+        .collect();
+    cells.shuffle(rng);
+    cells.truncate(conf.attraction_points);
+
+    cells
+}
+
+fn add_node(nodes: &mut Vec<Node>, occupied: &mut HashSet<(i32, i32)>, parent: usize, stick: Stick) {
+    let point = nodes[parent].point + stick.cursor_move().expect("Growth sticks are branches.");
+    occupied.insert(cell(point));
+    nodes.push(Node {point, stick: Some(stick), children: Vec::new()});
+
+    let child = nodes.len() - 1;
+    nodes[parent].children.push(child);
+}
+
+//Grows the trunk straight up, then towards the attraction points.
+fn grow(rng: &mut rand::rngs::ThreadRng, conf: &ColonizationConfig) -> Vec<Node> {
+    //The root, and the /|\ underneath it.
+    let mut occupied: HashSet<(i32, i32)> = [(0, 0), (-1, -1), (0, -1), (1, -1)].iter().copied().collect();
+    let mut nodes = vec![Node {point: Point {x: 0, y: 0}, stick: None, children: Vec::new()}];
+
+    for _ in 0..conf.trunk_height.min(conf.max_sticks) {
+        let top = nodes.len() - 1;
+        add_node(&mut nodes, &mut occupied, top, Stick::UpBranch);
+    }
+
+    let mut attraction_points = scatter_attraction_points(rng, conf);
+    let influence = (conf.influence_radius * conf.influence_radius) as i64;
+    let kill = (conf.kill_distance * conf.kill_distance) as i64;
+
+    //Every node but the root is a stick.
+    while nodes.len() <= conf.max_sticks {
+        attraction_points.retain(|point| nodes.iter().all(|node| distance_squared(node.point, *point) > kill));
+
+        //Every node is pulled towards the points it's the nearest node to.
+        let mut pulls = vec![(0.0f64, 0.0f64); nodes.len()];
+        for point in &attraction_points {
+            let nearest = nodes.iter().enumerate()
+                .map(|(i, node)| (distance_squared(node.point, *point), i))
+                .min()
+                .filter(|(distance, _)| *distance <= influence);
+
+            if let Some((distance, i)) = nearest {
+                let length = (distance as f64).sqrt();
+                pulls[i].0 += (point.x - nodes[i].point.x) as f64 / length;
+                pulls[i].1 += (point.y - nodes[i].point.y) as f64 / length;
+            }
+        }
+
+        let mut grew = false;
+        for (i, pull) in pulls.into_iter().enumerate() {
+            if pull == (0.0, 0.0) || nodes.len() > conf.max_sticks {
+                continue;
+            }
+
+            //Whichever free cell is most in the direction of the pull.
+            let point = nodes[i].point;
+            let best = GROWTH_STICKS.iter()
+                .map(|stick| (*stick, stick.cursor_move().expect("Growth sticks are branches.")))
+                .filter(|(_, step)| !occupied.contains(&cell(point + *step)))
+                .map(|(stick, step)| {
+                    let length = ((step.x * step.x + step.y * step.y) as f64).sqrt();
+                    ((step.x as f64 * pull.0 + step.y as f64 * pull.1) / length, stick)
+                })
+                .filter(|(alignment, _)| *alignment > 0.0)
+                .max_by(|a, b| a.0.partial_cmp(&b.0).expect("Alignments aren't NaN."));
+
+            if let Some((_, stick)) = best {
+                add_node(&mut nodes, &mut occupied, i, stick);
+                grew = true;
+            }
+        }
+
+        if !grew {
+            break;
+        }
+    }
+
+    nodes
+}
+
+fn subtree_size(nodes: &[Node], i: usize) -> usize {
+    1 + nodes[i].children.iter().map(|&child| subtree_size(nodes, child)).sum::<usize>()
+}
+
+//Leaves only go in cells nothing else is in yet.
+fn write_leaves(rng: &mut rand::rngs::ThreadRng, conf: &ColonizationConfig, point: Point, occupied: &mut HashSet<(i32, i32)>, tree_string: &mut String) {
+    let mut leaves: Vec<Stick> = LEAF_STICKS.iter()
+        .copied()
+        .filter(|leaf| !occupied.contains(&cell(point + leaf.cursor_move().expect("Leaves move the cursor."))))
+        .collect();
+    leaves.shuffle(rng);
+    leaves.truncate(rng.gen_range(1..=conf.max_leaves_per_tip.max(1)));
+
+    if leaves.is_empty() {
+        return;
+    }
+
+    tree_string.push(LeafSpawn::to_char());
+    for (j, leaf) in leaves.iter().enumerate() {
+        if j > 0 {
+            tree_string.push(LeafReturn::to_char());
+        }
+        occupied.insert(cell(point + leaf.cursor_move().expect("Leaves move the cursor.")));
+        tree_string.push(leaf.to_char());
+    }
+}
+
+//Every child but the biggest branches off, then the biggest carries on.
+fn write_node(rng: &mut rand::rngs::ThreadRng, conf: &ColonizationConfig, nodes: &[Node], i: usize, occupied: &mut HashSet<(i32, i32)>, tree_string: &mut String) {
+    let mut children = nodes[i].children.clone();
+    if children.is_empty() {
+        write_leaves(rng, conf, nodes[i].point, occupied, tree_string);
+        return;
+    }
+    children.sort_by_key(|&child| subtree_size(nodes, child));
+
+    let (main, others) = children.split_last().expect("There's at least one child.");
+    for &child in others {
+        tree_string.push(BranchIndicator::to_char());
+        write_stick(rng, conf, nodes, child, occupied, tree_string);
+        tree_string.push(BranchReturn::to_char());
+    }
+    write_stick(rng, conf, nodes, *main, occupied, tree_string);
+}
+
+fn write_stick(rng: &mut rand::rngs::ThreadRng, conf: &ColonizationConfig, nodes: &[Node], i: usize, occupied: &mut HashSet<(i32, i32)>, tree_string: &mut String) {
+    tree_string.push(nodes[i].stick.expect("Only the root doesn't have a stick.").to_char());
+    write_node(rng, conf, nodes, i, occupied, tree_string);
+}
+
+pub fn gen(rng: &mut rand::rngs::ThreadRng, conf: &ColonizationConfig) -> String {
+    let nodes = grow(rng, conf);
+
+    let mut occupied: HashSet<(i32, i32)> = nodes.iter().map(|node| cell(node.point)).collect();
+    occupied.extend([(-1, -1), (0, -1), (1, -1)].iter().copied());
+
+    let mut tree_string = String::new();
+    if nodes.len() > 1 {
+        write_node(rng, conf, &nodes, 0, &mut occupied, &mut tree_string);
+    }
+
+    tree_string
+}
+
+#[cfg(test)]
+mod tree_gen_colonization_tests {
+    use super::*;
+    use crate::sticks::cells_landed_on;
+
+    #[test]
+    fn sticks_never_overlap() {
+        let mut rng = rand::thread_rng();
+
+        for crown in [CrownShape::Ellipse, CrownShape::Cone, CrownShape::Umbrella].iter() {
+            let mut conf = ColonizationConfig::new();
+            conf.crown = *crown;
+
+            let tree_string = gen(&mut rng, &conf);
+            let cells = cells_landed_on(&tree_string, Point {x: 0, y: 0});
+            let unique: HashSet<_> = cells.iter().collect();
+            assert_eq!(cells.len(), unique.len(), "{}", tree_string);
+            assert!(cells.len() > conf.trunk_height, "{}", tree_string);
+        }
+    }
+
+    #[test]
+    fn stays_under_max_sticks() {
+        let mut rng = rand::thread_rng();
+        let mut conf = ColonizationConfig::new();
+        conf.max_sticks = 10;
+
+        let tree_string = gen(&mut rng, &conf);
+        let sticks = tree_string.chars().filter(|c| "|\\/".contains(*c)).count();
+        assert!(sticks <= 10);
+    }
+
+    #[test]
+    fn crown_shapes() {
+        assert_eq!("cone".parse::<CrownShape>(), Ok(CrownShape::Cone));
+        assert!("blob".parse::<CrownShape>().is_err());
+    }
+}
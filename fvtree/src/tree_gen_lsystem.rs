@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::choose_amongst::Lottery;
+use crate::parser::MAX_EXPANDED_STICKS;
+use crate::sticks::Stick;
+
+//An L-system over the stick alphabet: start with the axiom, then every generation, rewrite
+//every symbol with one of its rules, all at once.
+//
+//Symbols are sticks, or uppercase letters, which are variables: they're there to be rewritten,
+//and whatever's left of them after the last generation is dropped.
+//Symbols without a rule stay as they are. Symbols with several rules pick one at random
+//every time, weighted, so the same L-system grows a different plant every time.
+pub struct LSystem {
+    axiom: String,
+    //Every symbol's successors and their weights, in the order they were added.
+    rules: HashMap<char, Vec<(String, usize)>>,
+}
+
+//Built-in L-systems, written the same way as an L-system file.
+pub const LSYSTEM_PRESETS: &[(&str, &str)] = &[
+    ("binary", "\
+#Every tip forks in two, and every stick doubles, so it keeps its shape as it grows.
+axiom: X
+X -> |y\\Xr/X
+| -> ||
+\\ -> \\\\
+/ -> //
+"),
+    ("weed", "\
+#Shoots that wander up, and mostly flower at the tips.
+axiom: |X
+X -> |yt\\Xr/X 2
+X -> \\y|Xr|X 1
+X -> /y|Xr|X 1
+X -> o<*l>^*
+"),
+    ("fern", "\
+#A stem with fronds coming off either side, which keep growing until they leaf out.
+axiom: |S
+S -> ||y\\Lry/RrS
+L -> \\L 2
+L -> ot<lb 1
+R -> /R 2
+R -> ou>lm 1
+"),
+];
+
+fn is_variable(c: char) -> bool {
+    c.is_ascii_uppercase()
+}
+
+fn check_symbols(symbols: &str) -> Result<(), &'static str> {
+    if symbols.chars().all(|c| is_variable(c) || Stick::try_from(c).is_ok()) {
+        Ok(())
+    }
+    else {
+        Err("L-system symbols must be sticks or uppercase letters.")
+    }
+}
+
+impl LSystem {
+    pub fn new(axiom: &str) -> Result<LSystem, &'static str> {
+        check_symbols(axiom)?;
+
+        Ok(LSystem {
+            axiom: axiom.to_string(),
+            rules: HashMap::new(),
+        })
+    }
+
+    //Adds another way for symbol to be rewritten, weight times as likely as a rule with weight 1.
+    pub fn add_rule(&mut self, symbol: char, successor: &str, weight: usize) -> Result<(), &'static str> {
+        check_symbols(&symbol.to_string())?;
+        check_symbols(successor)?;
+        if weight == 0 {
+            return Err("L-system rule weights must be at least 1.");
+        }
+
+        self.rules.entry(symbol).or_default().push((successor.to_string(), weight));
+        Ok(())
+    }
+
+    //Reads an L-system written like this, one rule per line:
+    //
+    //    #A comment.
+    //    axiom: |X
+    //    X -> |y\Xr/X 2
+    //    X -> o^l<r
+    //
+    //The number after a rule is its weight, which is 1 if there isn't one.
+    pub fn parse(input: &str) -> Result<LSystem, &'static str> {
+        let mut axiom = None;
+        let mut rules = Vec::new();
+
+        for line in input.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix("axiom:") {
+                axiom = Some(rest.trim());
+                continue;
+            }
+
+            let (symbol, rest) = match line.split_once("->") {
+                Some((symbol, rest)) => (symbol.trim(), rest),
+                None => return Err("L-system lines must be an axiom, a rule or a comment."),
+            };
+
+            let mut symbol_chars = symbol.chars();
+            let symbol = match (symbol_chars.next(), symbol_chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err("L-system rules must rewrite exactly one symbol."),
+            };
+
+            let mut words = rest.split_whitespace();
+            let successor = words.next().unwrap_or("");
+            let weight = match words.next() {
+                Some(weight) => weight.parse::<usize>().map_err(|_| "L-system rule weights must be numbers.")?,
+                None => 1,
+            };
+            if words.next().is_some() {
+                return Err("L-system rules can't have anything after their weight.");
+            }
+
+            rules.push((symbol, successor, weight));
+        }
+
+        let mut lsystem = LSystem::new(axiom.ok_or("L-system is missing its axiom.")?)?;
+        for (symbol, successor, weight) in rules {
+            lsystem.add_rule(symbol, successor, weight)?;
+        }
+
+        Ok(lsystem)
+    }
+
+    pub fn from_file(path: &str) -> Result<LSystem, &'static str> {
+        let input = std::fs::read_to_string(path).map_err(|_| "Couldn't read the L-system file.")?;
+
+        LSystem::parse(&input)
+    }
+
+    pub fn preset(name: &str) -> Option<LSystem> {
+        LSYSTEM_PRESETS.iter()
+            .find(|(preset_name, _)| *preset_name == name)
+            .map(|(_, input)| LSystem::parse(input).expect("L-system presets should parse."))
+    }
+
+    //The tree string after generations rewrites, which errors if it isn't a tree string the
+    //renderer can draw, since rules can put sticks anywhere.
+    pub fn gen(&self, rng: &mut rand::rngs::ThreadRng, generations: usize) -> Result<String, &'static str> {
+        let mut symbols = self.axiom.clone();
+
+        for _ in 0..generations {
+            let mut next = String::with_capacity(symbols.len() * 2);
+
+            for c in symbols.chars() {
+                match self.rules.get(&c) {
+                    Some(successors) => next.push_str(self.choose_successor(rng, successors)),
+                    None => next.push(c),
+                }
+
+                if next.len() > MAX_EXPANDED_STICKS {
+                    return Err("L-system grew too many sticks, try fewer generations.");
+                }
+            }
+
+            symbols = next;
+        }
+
+        let tree_string: String = symbols.chars().filter(|c| !is_variable(*c)).collect();
+        check(&tree_string)?;
+
+        Ok(tree_string)
+    }
+
+    fn choose_successor<'a>(&self, rng: &mut rand::rngs::ThreadRng, successors: &'a [(String, usize)]) -> &'a str {
+        if successors.len() == 1 {
+            return &successors[0].0;
+        }
+
+        let mut lottery: Lottery<usize> = Lottery::build(rng);
+        for (i, (_, weight)) in successors.iter().enumerate() {
+            lottery.add_with_bias(i, *weight);
+        }
+
+        &successors[lottery.choose()].0
+    }
+}
+
+//The renderer trusts every BranchReturn to have a BranchIndicator to go back to, and every
+//LeafReturn and decoration to come after a LeafSpawn, without branch sticks in between.
+//Every BranchIndicator needs its BranchReturn too, or the tree string can't be edited or bred.
+fn check(tree_string: &str) -> Result<(), &'static str> {
+    let mut open_branches = 0;
+    let mut in_leaflet = false;
+
+    for c in tree_string.chars() {
+        match Stick::try_from(c)? {
+            Stick::BranchIndicator => open_branches += 1,
+            Stick::BranchReturn => {
+                if open_branches == 0 {
+                    return Err("L-system grew a BranchReturn without a BranchIndicator.");
+                }
+                open_branches -= 1;
+            },
+            Stick::LeafSpawn => in_leaflet = true,
+            Stick::LeafReturn if !in_leaflet => return Err("L-system grew a LeafReturn outside of a leaflet."),
+            stick if stick.is_decoration() && !in_leaflet => return Err("L-system grew a decoration outside of a leaflet."),
+            stick if !stick.is_control_char() && !stick.is_leaf() && !stick.is_decoration() => in_leaflet = false,
+            _ => (),
+        }
+    }
+
+    if open_branches > 0 {
+        return Err("L-system grew a BranchIndicator without a BranchReturn.");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tree_gen_lsystem_tests {
+    use super::*;
+    use crate::{Fvtree, FvtreeString};
+
+    #[test]
+    fn rewrites_every_generation() {
+        let mut rng = rand::thread_rng();
+        let mut lsystem = LSystem::new("X").unwrap();
+        lsystem.add_rule('X', "|yXr", 1).unwrap();
+
+        assert_eq!(lsystem.gen(&mut rng, 0), Ok("".to_string()));
+        assert_eq!(lsystem.gen(&mut rng, 3), Ok("|y|y|yrrr".to_string()));
+    }
+
+    #[test]
+    fn parse_rules() {
+        let lsystem = LSystem::parse("#Comment.\naxiom: |X\n\nX -> /X 3\nX ->\n").unwrap();
+        assert_eq!(lsystem.axiom, "|X");
+        assert_eq!(lsystem.rules[&'X'], vec![("/X".to_string(), 3), ("".to_string(), 1)]);
+
+        assert!(LSystem::parse("X -> |").is_err());
+        assert!(LSystem::parse("axiom: X\nXY -> |").is_err());
+        assert!(LSystem::parse("axiom: X\nX -> | 0").is_err());
+        assert!(LSystem::parse("axiom: X\nX -> #").is_err());
+    }
+
+    #[test]
+    fn invalid_trees() {
+        let mut rng = rand::thread_rng();
+        assert!(LSystem::new("|r").unwrap().gen(&mut rng, 1).is_err());
+        assert!(LSystem::new("|o^|l").unwrap().gen(&mut rng, 1).is_err());
+        assert!(LSystem::new("|y/").unwrap().gen(&mut rng, 1).is_err());
+        assert!(LSystem::new("|y/y\\r").unwrap().gen(&mut rng, 1).is_err());
+    }
+
+    #[test]
+    fn presets_grow() {
+        let mut rng = rand::thread_rng();
+
+        for (name, _) in LSYSTEM_PRESETS {
+            for _ in 0..10 {
+                let tree_string = LSystem::preset(name).unwrap().gen(&mut rng, 4).unwrap();
+                assert!(!tree_string.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn lsystem_presets_render() {
+        for (name, _) in LSYSTEM_PRESETS {
+            let fvtree_string = FvtreeString::new_lsystem(&LSystem::preset(name).unwrap(), 5).unwrap();
+            assert!(Fvtree::build(&fvtree_string).is_ok());
+        }
+    }
+}
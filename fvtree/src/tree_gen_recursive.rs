@@ -1,6 +1,12 @@
 use rand::Rng;
 
+use common::Point;
+
+use crate::sticks;
 use crate::sticks::Stick;
+use std::convert::TryFrom;
+use std::collections::HashSet;
+
 use crate::confstats::{Stats, Config, TreeStats, BranchStats};
 
 //For returning BranchReturn char 'r' or BranchIndicator char 'y'.
@@ -12,27 +18,103 @@ use crate::sticks::{LeafSpawn, LeafReturn};
 
 use crate::choose_amongst::Lottery;
 
-//Returns a "stick" for the trunk, or None.
-fn gen_trunk_stick_or_stop(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config) -> Option<Stick> {
-    let mut lottery: Lottery<Option<Stick>> = Lottery::build(rng);
+pub const BRANCH_STICKS: [Stick; 3] = [Stick::UpBranch, Stick::UpLeftBranch, Stick::UpRightBranch];
+pub const LEAF_STICKS: [Stick; 8] = [
+    Stick::UpLeaf, Stick::LeftLeaf, Stick::RightLeaf, Stick::DownLeaf,
+    Stick::UpLeftLeaf, Stick::UpRightLeaf, Stick::DownLeftLeaf, Stick::DownRightLeaf,
+];
 
-    if stats.b.height < conf.t.height_when_trunk_forced_to_branch || stats.t.has_branched {
-        lottery.add(Some(Stick::UpBranch));
-        lottery.add(Some(Stick::UpLeftBranch));
-        lottery.add(Some(Stick::UpRightBranch));
+//stick's weight out of weights, which are in the same order as sticks.
+fn weight_of(stick: Stick, sticks: &[Stick], weights: &[usize]) -> usize {
+    let i = sticks.iter().position(|s| *s == stick).expect("Weights are given for every stick.");
+    weights[i]
+}
+
+//Whether the tree stays inside conf.t.max_width and conf.t.max_height with stick put down,
+//and its mirror image too, if conf.t.symmetric is going to put that down as well.
+fn fits(stats: &Stats, conf: &Config, stick: Stick) -> bool {
+    if conf.t.max_width.is_none() && conf.t.max_height.is_none() {
+        return true;
+    }
+
+    let landing = stats.landing(stick);
+    let mirrored = if conf.t.symmetric {Point {x: -landing.x, y: landing.y}} else {landing};
+    let (width, height) = stats.t.size_with(&[landing, mirrored]);
+
+    conf.t.max_width.is_none_or(|max_width| width <= max_width)
+        && conf.t.max_height.is_none_or(|max_height| height <= max_height)
+}
+
+//Whether stick would land somewhere free with conf.t.avoid_collisions,
+//and inside conf.t.silhouette.
+fn may_land(stats: &Stats, conf: &Config, stick: Stick) -> bool {
+    (!conf.t.avoid_collisions || stats.is_free(stick))
+        && conf.t.silhouette.is_none_or(|silhouette| silhouette.contains(stats.landing(stick)))
+}
+
+//Only the sticks that fit and may land where they would,
+//unless none of them may, then just the ones that fit.
+fn sticks_to_choose_from(stats: &Stats, conf: &Config, sticks: &[Stick]) -> Vec<Stick> {
+    let fitting: Vec<Stick> = sticks.iter().copied().filter(|stick| fits(stats, conf, *stick)).collect();
+
+    if fitting.iter().any(|stick| may_land(stats, conf, *stick)) {
+        fitting.into_iter().filter(|stick| may_land(stats, conf, *stick)).collect()
+    }
+    else {
+        fitting
+    }
+}
+
+//Whether none of sticks fit, so it has to stop, whatever the Config says.
+fn is_out_of_room(stats: &Stats, conf: &Config, sticks: &[Stick]) -> bool {
+    sticks.iter().all(|stick| !fits(stats, conf, *stick))
+}
+
+//Whether none of sticks may land where they would, when there's the option to stop instead,
+//or it's out of room.
+//Branching wouldn't help, the branch would start from the same spot.
+fn is_boxed_in(stats: &Stats, conf: &Config, sticks: &[Stick], can_stop: bool) -> bool {
+    (can_stop && sticks.iter().all(|stick| !may_land(stats, conf, *stick)))
+        || is_out_of_room(stats, conf, sticks)
+}
+
+//The leaves conf.t.silhouette lets grow.
+fn leaf_sticks(conf: &Config) -> Vec<Stick> {
+    LEAF_STICKS.iter()
+        .copied()
+        .filter(|leaf| conf.t.silhouette.is_none_or(|silhouette| silhouette.grows_leaf(*leaf)))
+        .collect()
+}
+
+//Returns a "stick" for the trunk, or None.
+fn gen_trunk_stick_or_stop<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config) -> Option<Stick> {
+    let mut lottery: Lottery<Option<Stick>, R> = Lottery::build(rng);
+
+    //A symmetric tree's trunk goes straight up the middle.
+    let trunk_sticks: &[Stick] = if conf.t.symmetric {&[Stick::UpBranch]} else {&BRANCH_STICKS};
+    let can_stop = (stats.b.height >= conf.t.min_trunk_height && stats.t.num_sticks >= conf.t.min_sticks)
+        || is_out_of_room(stats, conf, trunk_sticks);
+    let boxed_in = is_boxed_in(stats, conf, trunk_sticks, can_stop);
+
+    if (stats.b.height < conf.t.height_when_trunk_forced_to_branch || stats.t.has_branched) && !boxed_in {
+        for stick in sticks_to_choose_from(stats, conf, trunk_sticks) {
+            lottery.add_with_bias(Some(stick), weight_of(stick, &BRANCH_STICKS, &conf.t.stick_weights));
+        }
     }
 
     //conf.t.height_when_trunk_forced_to_branch is always >=
     //conf.t.min_height_before_trunk_can_branch,
-    //so BranchIndicator is always a possible output when the other sticks aren't.
-    if stats.b.height >= conf.t.min_height_before_trunk_can_branch {
+    //so BranchIndicator is always a possible output when the other sticks aren't,
+    //unless the trunk is boxed in, and then it can stop.
+    if stats.b.height >= conf.t.min_height_before_trunk_can_branch && !boxed_in {
         //The trunk may also branch.
-        lottery.add(Some(Stick::BranchIndicator));
+        lottery.add_with_bias(Some(Stick::BranchIndicator), conf.t.branch_weight);
     }
 
-    if stats.b.height >= conf.t.min_trunk_height && stats.t.num_sticks >= conf.t.min_sticks {
-        //We've reached the min_trunk_height and may stop now.
-        lottery.add(None);
+    if can_stop {
+        //We've reached the min_trunk_height and may stop now,
+        //or there's no room left to grow.
+        lottery.add_with_bias(None, conf.t.stop_weight);
     }
 
     let output = lottery.choose();
@@ -43,6 +125,7 @@ fn gen_trunk_stick_or_stop(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, c
             //TODO: integrate height into add_one_stick().
             stats.b.height += 1;
             stats.add_one_stick();
+            stats.put_down(stick);
         }
         //Is BranchIndicator.
         else {
@@ -54,25 +137,32 @@ fn gen_trunk_stick_or_stop(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, c
     return output;
 }
 
-fn gen_branch_stick_or_stop(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config) -> Option<Stick> {
-    let mut lottery: Lottery<Option<Stick>> = Lottery::build(rng);
+fn gen_branch_stick_or_stop<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config) -> Option<Stick> {
+    let mut lottery: Lottery<Option<Stick>, R> = Lottery::build(rng);
+    let depth_conf = conf.b.at_depth(stats.t.branch_depth);
 
-    lottery.add(Some(Stick::UpBranch));
-    lottery.add(Some(Stick::UpLeftBranch));
-    lottery.add(Some(Stick::UpRightBranch));
+    let out_of_room = is_out_of_room(stats, conf, &BRANCH_STICKS);
+    let boxed_in = is_boxed_in(stats, conf, &BRANCH_STICKS, stats.b.num_sticks >= depth_conf.min_sticks);
 
-    if stats.b.num_sticks >= conf.b.min_sticks_before_branch {
-        lottery.add(Some(Stick::BranchIndicator));
+    if !boxed_in {
+        for stick in sticks_to_choose_from(stats, conf, &BRANCH_STICKS) {
+            lottery.add_with_bias(Some(stick), weight_of(stick, &BRANCH_STICKS, &conf.b.stick_weights));
+        }
     }
 
-    if stats.b.num_sticks >= conf.b.min_sticks {
-        //We've attained min_sticks and may stop now.
-        lottery.add(None);
+    if stats.b.num_sticks >= conf.b.min_sticks_before_branch && depth_conf.can_branch && !boxed_in {
+        lottery.add_with_bias(Some(Stick::BranchIndicator), depth_conf.branch_weight);
     }
 
-    if stats.b.num_sticks >= conf.b.min_sticks && conf.b.allow_dead_branches {
+    if stats.b.num_sticks >= depth_conf.min_sticks || out_of_room {
+        //We've attained min_sticks and may stop now,
+        //or there's no room left to grow.
+        lottery.add_with_bias(None, conf.b.stop_weight);
+    }
+
+    if stats.b.num_sticks >= depth_conf.min_sticks && conf.b.allow_dead_branches {
         //We've attained min_sticks and may stop with a dead branch.
-        lottery.add(Some(Stick::BranchReturn));
+        lottery.add_with_bias(Some(Stick::BranchReturn), conf.b.stop_weight);
     }
 
     let output = lottery.choose();
@@ -80,6 +170,7 @@ fn gen_branch_stick_or_stop(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats,
     if let Some(stick) = output {
         if !stick.is_control_char() {
             stats.add_one_stick();
+            stats.put_down(stick);
         }
         else {
             match stick {
@@ -98,37 +189,44 @@ fn gen_branch_stick_or_stop(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats,
     return output;
 }
 
-fn gen_leaf_stick_or_stop(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config) -> Option<Stick> {
+fn gen_leaf_stick_or_stop<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config) -> Option<Stick> {
     if !stats.b.is_leaf_state {
+        //Not even room for one leaf, so no leaflet to spawn.
+        if is_out_of_room(stats, conf, &leaf_sticks(conf)) {
+            return None;
+        }
         stats.b.is_leaf_state = true;
+        stats.b.leaf_spawn_point = stats.b.cursor;
         return Some(Stick::LeafSpawn);
     }
 
-    let mut lottery: Lottery<Option<Stick>> = Lottery::build(rng);
-
-    if stats.b.num_leaves_in_leaflet < conf.b.max_leaves_in_leaflet {
-        lottery.add(Some(Stick::UpLeaf));
-        lottery.add(Some(Stick::LeftLeaf));
-        lottery.add(Some(Stick::RightLeaf));
-        lottery.add(Some(Stick::DownLeaf));
-
-        lottery.add(Some(Stick::UpLeftLeaf));
-        lottery.add(Some(Stick::UpRightLeaf));
-        lottery.add(Some(Stick::DownLeftLeaf));
-        lottery.add(Some(Stick::DownRightLeaf));
+    let mut lottery: Lottery<Option<Stick>, R> = Lottery::build(rng);
+    let depth_conf = conf.b.at_depth(stats.t.branch_depth);
+
+    let can_return = stats.b.num_leaves_in_leaflet >= conf.b.min_leaves_in_leaflet
+        && stats.b.num_leaflets < depth_conf.max_leaflets;
+    //We've attained min_leaves and may stop now,
+    //but not if we need more leaves in current leaflet,
+    //and not if we need more leaflets.
+    //Or when there's no room left for any leaves.
+    let leaf_sticks = leaf_sticks(conf);
+    let can_stop = ((stats.b.num_leaflets > 0 || conf.b.allow_dead_branches)
+        && stats.b.num_leaves_in_leaflet >= conf.b.min_leaves_in_leaflet
+        && stats.b.num_leaflets >= depth_conf.min_leaflets)
+        || is_out_of_room(stats, conf, &leaf_sticks);
+
+    if stats.b.num_leaves_in_leaflet < conf.b.max_leaves_in_leaflet
+    && !is_boxed_in(stats, conf, &leaf_sticks, can_return || can_stop) {
+        for stick in sticks_to_choose_from(stats, conf, &leaf_sticks) {
+            lottery.add_with_bias(Some(stick), weight_of(stick, &LEAF_STICKS, &conf.b.leaf_weights));
+        }
     }
 
-    if stats.b.num_leaves_in_leaflet >= conf.b.min_leaves_in_leaflet
-    && stats.b.num_leaflets < conf.b.max_leaflets {
+    if can_return {
         lottery.add(Some(Stick::LeafReturn));
     }
 
-    if (stats.b.num_leaflets > 0 || conf.b.allow_dead_branches)
-    && stats.b.num_leaves_in_leaflet >= conf.b.min_leaves_in_leaflet
-    && stats.b.num_leaflets >= conf.b.min_leaflets {
-        //We've attained min_leaves and may stop now,
-        //but not if we need more leaves in current leaflet,
-        //and not if we need more leaflets.
+    if can_stop {
         lottery.add(None);
     }
 
@@ -137,12 +235,14 @@ fn gen_leaf_stick_or_stop(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, co
     if let Some(stick) = output {
         if !stick.is_control_char() {
             stats.add_one_leaf();
+            stats.put_down(stick);
         }
         else {
             match stick {
                 Stick::LeafReturn => {
                     stats.b.num_leaves_in_leaflet = 0;
                     stats.b.num_leaflets += 1;
+                    stats.b.cursor = stats.b.leaf_spawn_point;
                 },
                 _ => panic!("Undefined control character!"),
             }
@@ -152,7 +252,36 @@ fn gen_leaf_stick_or_stop(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, co
     return output;
 }
 
-fn gen_leaves(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config) -> String {
+//Returns a decoration to hang off of the leaf that was just placed, or None.
+fn gen_decoration_or_none<R: Rng>(rng: &mut R, conf: &Config) -> Option<Stick> {
+    let chances = [
+        (Stick::Fruit, conf.b.fruit_chance),
+        (Stick::Flower, conf.b.flower_chance),
+        (Stick::Blossom, conf.b.blossom_chance),
+    ];
+
+    let total_chance: usize = chances.iter().map(|(_, chance)| chance).sum();
+    if total_chance == 0 {
+        return None;
+    }
+
+    let mut lottery: Lottery<Option<Stick>, R> = Lottery::build(rng);
+
+    for (decoration, chance) in chances.iter() {
+        if *chance > 0 {
+            lottery.add_with_bias(Some(*decoration), *chance);
+        }
+    }
+
+    //The chances are out of 100, whatever is left over is the chance of no decoration.
+    if total_chance < 100 {
+        lottery.add_with_bias(None, 100 - total_chance);
+    }
+
+    lottery.choose()
+}
+
+fn gen_leaves<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config) -> String {
     let mut leaves = String::new();
 
     //There may be more than one LeafSpawn per leaves in future versions,
@@ -166,7 +295,13 @@ fn gen_leaves(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config)
             Some(Stick::LeafReturn) => {
                 leaves.push(LeafReturn::to_char());
             },
-            Some(s) => leaves.push(s.to_char()),
+            Some(s) => {
+                leaves.push(s.to_char());
+
+                if let Some(decoration) = gen_decoration_or_none(rng, conf) {
+                    leaves.push(decoration.to_char());
+                }
+            },
             None => {
                 stats.b.is_leaf_state = false;
                 stats.b.num_leaves_in_leaflet = 0;
@@ -180,10 +315,11 @@ fn gen_leaves(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config)
     return leaves;
 }
 
-fn gen_branches(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config) -> String {
+fn gen_branches<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config) -> String {
     let mut branch_str = String::new();
+    let max_sticks = conf.b.at_depth(stats.t.branch_depth).max_sticks;
 
-    while stats.b.num_sticks < conf.b.max_sticks {
+    while stats.b.num_sticks < max_sticks {
         let stick;
 
         match gen_branch_stick_or_stop(rng, stats, conf) {
@@ -218,10 +354,11 @@ fn gen_branches(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Confi
     return branch_str;
 }
 
-fn branch(current_branch: &mut String, rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config) {
+fn branch<R: Rng>(current_branch: &mut String, rng: &mut R, stats: &mut Stats, conf: &Config) {
     current_branch.push(BranchIndicator::to_char());
     let current_branch_stats = stats.b;
     stats.b = BranchStats::new();
+    stats.b.cursor = current_branch_stats.cursor;
 
     let branch = gen_branches(rng, stats, conf);
 
@@ -230,7 +367,19 @@ fn branch(current_branch: &mut String, rng: &mut rand::rngs::ThreadRng, stats: &
     current_branch.push_str(&branch);
 }
 
-fn gen_trunk(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config) -> String {
+//Puts the mirror image of the branch that starts at branch_start on the other side of the trunk.
+fn mirror_branch(trunk: &mut String, branch_start: usize, stats: &mut Stats) {
+    let mirrored = sticks::mirror(&trunk[branch_start..]).expect("Branches are made of canonical sticks.");
+
+    for landing in sticks::cells_landed_on(&mirrored, stats.b.cursor) {
+        stats.t.occupy(landing);
+        stats.t.num_sticks += 1;
+    }
+
+    trunk.push_str(&mirrored);
+}
+
+fn gen_trunk<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config) -> String {
     let mut trunk: String = String::new();
 
     //The trunk is technically a branch, though it will still generate if
@@ -240,7 +389,11 @@ fn gen_trunk(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config)
 
         match gen_trunk_stick_or_stop(rng, stats, conf) {
             Some(Stick::BranchIndicator) => {
+                let branch_start = trunk.len();
                 branch(&mut trunk, rng, stats, conf);
+                if conf.t.symmetric {
+                    mirror_branch(&mut trunk, branch_start, stats);
+                }
                 continue;
             },
             Some(s) => stick = s,
@@ -253,6 +406,584 @@ fn gen_trunk(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config)
     return trunk;
 }
 
-pub fn gen(rng: &mut rand::rngs::ThreadRng, stats: &mut Stats, conf: &Config) -> String {
+pub fn gen<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config) -> String {
     return gen_trunk(rng, stats, conf);
 }
+
+//Growing a tree that's already there, a little at a time, see grow().
+
+//A branch already in the tree.
+struct GrownBranch {
+    //Where its BranchReturn is.
+    end: usize,
+    //Where it branches off from, and how deep it is.
+    from: Point,
+    depth: usize,
+    //Where its leaflets spawn from and how many there are, if it has any.
+    leaf_spawn_point: Option<Point>,
+    leaflets: usize,
+    ends_in_leaf_return: bool,
+}
+
+//Everything grow() needs to know about a tree string.
+struct GrownTree {
+    branches: Vec<GrownBranch>,
+    trunk_height: usize,
+    trunk_tip: Point,
+    //Where a leaf or a leaf spawn is drawn, so a branch can't sprout from there without
+    //drawing a Y over it.
+    covered: HashSet<(i32, i32)>,
+}
+
+//Follows the cursor through tree_string the way Fvtree::build does, putting down every stick in
+//stats as it goes.
+fn survey(tree_string: &str, stats: &mut Stats) -> Result<GrownTree, &'static str> {
+    let chars: Vec<char> = tree_string.chars().collect();
+    let mut cursor = Point {x: 0, y: 0};
+    let mut open: Vec<GrownBranch> = Vec::new();
+    let mut branches = Vec::new();
+    let mut trunk_height = 0;
+    let mut covered = HashSet::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        match Stick::try_from(c)? {
+            Stick::BranchIndicator => open.push(GrownBranch {
+                end: i,
+                from: cursor,
+                depth: open.len() + 1,
+                leaf_spawn_point: None,
+                leaflets: 0,
+                ends_in_leaf_return: false,
+            }),
+            Stick::BranchReturn => {
+                let mut branch = open.pop().ok_or("Found a BranchReturn but not a corresponding BranchIndicator.")?;
+                branch.end = i;
+                branch.ends_in_leaf_return = i > 0 && chars[i - 1] == LeafReturn::to_char();
+                //Each leaf return ends a leaflet, and so does the branch return, unless it comes
+                //right after one.
+                if branch.leaf_spawn_point.is_some() && !branch.ends_in_leaf_return {
+                    branch.leaflets += 1;
+                }
+                cursor = branch.from;
+                branches.push(branch);
+            },
+            Stick::LeafSpawn => {
+                covered.insert((cursor.x, cursor.y));
+                if let Some(branch) = open.last_mut() {
+                    branch.leaf_spawn_point = Some(cursor);
+                }
+            },
+            Stick::LeafReturn => {
+                if let Some(branch) = open.last_mut() {
+                    branch.leaflets += 1;
+                    cursor = branch.leaf_spawn_point.ok_or("Found a LeafReturn but not a corresponding LeafSpawn.")?;
+                }
+            },
+            stick if stick.is_decoration() => (),
+            stick => {
+                cursor += stick.cursor_move()?;
+                stats.t.occupy((cursor.x, cursor.y));
+                if stick.is_leaf() {
+                    covered.insert((cursor.x, cursor.y));
+                }
+                if open.is_empty() {
+                    trunk_height += 1;
+                }
+            },
+        }
+    }
+
+    if !open.is_empty() {
+        return Err("Found a BranchIndicator but not a corresponding BranchReturn.");
+    }
+
+    Ok(GrownTree {branches, trunk_height, trunk_tip: cursor, covered})
+}
+
+//Whether piece, put down from start, would draw over anything in taken, or put a Y or an O on it,
+//which would stop the stick that drew it from being drawn. The Y piece starts with is already
+//there.
+fn draws_over(piece: &str, start: Point, taken: &HashSet<(i32, i32)>) -> bool {
+    let mut cursor = start;
+    let mut branch_points = Vec::new();
+    let mut leaf_spawn_point = start;
+    let is_taken = |point: Point| taken.contains(&(point.x, point.y));
+
+    for c in piece.chars() {
+        match Stick::try_from(c).expect("Pieces are made of canonical sticks.") {
+            Stick::BranchIndicator => {
+                if cursor != start && is_taken(cursor) {
+                    return true;
+                }
+                branch_points.push(cursor);
+            },
+            Stick::BranchReturn => cursor = branch_points.pop().unwrap_or(start),
+            Stick::LeafSpawn => {
+                if is_taken(cursor) {
+                    return true;
+                }
+                leaf_spawn_point = cursor;
+            },
+            Stick::LeafReturn => cursor = leaf_spawn_point,
+            stick if stick.is_decoration() => (),
+            stick => {
+                cursor += stick.cursor_move().expect("Everything else is a branch or a leaf.");
+                if is_taken(cursor) {
+                    return true;
+                }
+            },
+        }
+    }
+
+    false
+}
+
+//How many branches grow() tries sprouting before giving up on one.
+const SPROUT_TRIES: usize = 8;
+
+//A new branch from from, depth deep, that doesn't draw over anything in taken, or None if it
+//couldn't come up with one.
+fn gen_sprout<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config, from: Point, depth: usize, taken: &HashSet<(i32, i32)>) -> Option<String> {
+    for _ in 0..SPROUT_TRIES {
+        let before = stats.t.clone();
+        stats.b = BranchStats::new();
+        stats.b.cursor = from;
+        stats.t.branch_depth = depth;
+
+        let mut sprout = String::new();
+        branch(&mut sprout, rng, stats, conf);
+        //A symmetric tree gets the same branch on the other side of the trunk.
+        if conf.t.symmetric && depth == 1 {
+            let start = sprout.len();
+            sprout.push_str(&sticks::mirror(&sprout).expect("Branches are made of canonical sticks."));
+            for landing in sticks::cells_landed_on(&sprout[start..], from) {
+                stats.t.occupy(landing);
+            }
+        }
+
+        if !draws_over(&sprout, from, taken) {
+            return Some(sprout);
+        }
+        stats.t = before;
+    }
+
+    None
+}
+
+//A new leaflet from the cursor, as many leaves as conf.b says, or fewer if it runs out of room.
+//Leaves only go where nothing's been put yet.
+fn gen_leaflet<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config) -> String {
+    let mut leaflet = String::new();
+    let num_leaves = rng.gen_range(conf.b.min_leaves_in_leaflet..=conf.b.max_leaves_in_leaflet.max(conf.b.min_leaves_in_leaflet));
+    let leaf_sticks = leaf_sticks(conf);
+
+    for _ in 0..num_leaves.max(1) {
+        let leaves: Vec<Stick> = sticks_to_choose_from(stats, conf, &leaf_sticks).into_iter()
+            .filter(|leaf| stats.is_free(*leaf))
+            .collect();
+        if leaves.is_empty() {
+            break;
+        }
+
+        let mut lottery: Lottery<Stick, R> = Lottery::build(rng);
+        for leaf in leaves {
+            lottery.add_with_bias(leaf, weight_of(leaf, &LEAF_STICKS, &conf.b.leaf_weights));
+        }
+        let leaf = lottery.choose();
+        stats.put_down(leaf);
+        leaflet.push(leaf.to_char());
+
+        if let Some(decoration) = gen_decoration_or_none(rng, conf) {
+            leaflet.push(decoration.to_char());
+        }
+    }
+
+    leaflet
+}
+
+//A stick for the top of the trunk that lands somewhere nothing's been put yet, or None.
+fn gen_trunk_top<R: Rng>(rng: &mut R, stats: &mut Stats, conf: &Config, trunk_tip: Point) -> Option<Stick> {
+    let trunk_sticks: &[Stick] = if conf.t.symmetric {&[Stick::UpBranch]} else {&BRANCH_STICKS};
+    stats.b = BranchStats::new();
+    stats.b.cursor = trunk_tip;
+
+    let sticks: Vec<Stick> = sticks_to_choose_from(stats, conf, trunk_sticks).into_iter()
+        .filter(|stick| stats.is_free(*stick))
+        .collect();
+    if sticks.is_empty() {
+        return None;
+    }
+
+    let mut lottery: Lottery<Stick, R> = Lottery::build(rng);
+    for stick in sticks {
+        lottery.add_with_bias(stick, weight_of(stick, &BRANCH_STICKS, &conf.t.stick_weights));
+    }
+    Some(lottery.choose())
+}
+
+//Grows tree_string a little: the trunk gets a stick taller, a new branch sprouts from somewhere
+//that's already branched, or the top of the trunk, and a branch with leaves gets another leaflet.
+//All as conf says, and nothing already there changes, everything new goes in between.
+//Nothing new is drawn over what's there either, apart from the Y of a branch sprouting off of the
+//top of the trunk, so the tree looks the same as it did, only bigger. If there's no room left to
+//grow into, it stays as it is.
+pub fn grow<R: Rng>(rng: &mut R, tree_string: &str, conf: &Config) -> Result<String, &'static str> {
+    //New sticks keep clear of what's there where they can, or there'd hardly be anywhere to grow.
+    let mut conf = conf.clone();
+    conf.t.avoid_collisions = true;
+    let conf = &conf;
+    let mut stats = Stats::new();
+    let tree = survey(tree_string, &mut stats)?;
+    let taken = stats.t.occupied.clone();
+    //Where each new piece goes in tree_string. Pieces at the same spot go in the order they're
+    //added.
+    let mut insertions: Vec<(usize, String)> = Vec::new();
+    let end = tree_string.chars().count();
+    let has_branched = !tree.branches.is_empty();
+
+    let trunk_grows = |stick: Stick, stats: &mut Stats, insertions: &mut Vec<(usize, String)>| {
+        stats.b = BranchStats::new();
+        stats.b.cursor = tree.trunk_tip;
+        stats.put_down(stick);
+        insertions.push((end, stick.to_char().to_string()));
+        tree.trunk_tip + stick.cursor_move().expect("Trunk sticks are branches.")
+    };
+
+    //The top of the trunk already has a stick drawn on it, which a branch sprouting from there
+    //would draw a Y over. So if the trunk can carry on first, the branch sprouts from its new top.
+    //If it can't, it has to branch before it can carry on, and that Y is the one thing grow()
+    //draws over.
+    let trunk_top = if tree.trunk_height < conf.t.max_trunk_height
+        && (tree.trunk_height < conf.t.height_when_trunk_forced_to_branch || has_branched) {
+        gen_trunk_top(rng, &mut stats, conf, tree.trunk_tip)
+    }
+    else {
+        None
+    };
+    let top_can_branch = match trunk_top {
+        Some(_) => tree.trunk_height + 1 >= conf.t.min_height_before_trunk_can_branch,
+        None => tree.trunk_height >= conf.t.min_height_before_trunk_can_branch && tree.trunk_height < conf.t.max_trunk_height,
+    };
+
+    //Sprout a branch next to one of the branches already there, or off of the top of the trunk.
+    let mut sprouts: Vec<(usize, Point, usize)> = tree.branches.iter()
+        .filter(|branch| branch.depth == 1 || conf.b.at_depth(branch.depth - 1).can_branch)
+        .filter(|branch| !tree.covered.contains(&(branch.from.x, branch.from.y)))
+        .map(|branch| (branch.end + 1, branch.from, branch.depth))
+        .collect();
+    let num_sprouts = sprouts.len() + top_can_branch as usize;
+    let choice = if num_sprouts > 0 {rng.gen_range(0..num_sprouts)} else {0};
+    let mut trunk_has_grown = false;
+    if choice == sprouts.len() && top_can_branch {
+        let from = match trunk_top {
+            Some(stick) => {
+                trunk_has_grown = true;
+                trunk_grows(stick, &mut stats, &mut insertions)
+            },
+            None => tree.trunk_tip,
+        };
+        sprouts.push((end, from, 1));
+    }
+    if let Some(&(at, from, depth)) = sprouts.get(choice) {
+        if let Some(sprout) = gen_sprout(rng, &mut stats, conf, from, depth, &taken) {
+            insertions.push((at, sprout));
+        }
+    }
+
+    //Otherwise the trunk carries on from its top, if it's allowed to without branching, or it
+    //just has.
+    let has_branched = has_branched || insertions.iter().any(|(at, _)| *at == end);
+    if !trunk_has_grown && tree.trunk_height < conf.t.max_trunk_height
+        && (tree.trunk_height < conf.t.height_when_trunk_forced_to_branch || has_branched) {
+        if let Some(stick) = gen_trunk_top(rng, &mut stats, conf, tree.trunk_tip) {
+            trunk_grows(stick, &mut stats, &mut insertions);
+        }
+    }
+
+    //Another leaflet for a branch that has room for one.
+    let leafy: Vec<&GrownBranch> = tree.branches.iter()
+        .filter(|branch| branch.leaf_spawn_point.is_some() && branch.leaflets < conf.b.at_depth(branch.depth).max_leaflets)
+        .collect();
+    if !leafy.is_empty() && !conf.t.dead_tree {
+        let branch = leafy[rng.gen_range(0..leafy.len())];
+        stats.b = BranchStats::new();
+        stats.b.cursor = branch.leaf_spawn_point.expect("Only branches with leaves are leafy.");
+
+        let leaves = gen_leaflet(rng, &mut stats, conf);
+        if !leaves.is_empty() {
+            let mut leaflet = String::new();
+            if !branch.ends_in_leaf_return {
+                leaflet.push(LeafReturn::to_char());
+            }
+            leaflet.push_str(&leaves);
+            insertions.push((branch.end, leaflet));
+        }
+    }
+
+    //Last spot first, so the spots before it stay put.
+    let mut grown: Vec<char> = tree_string.chars().collect();
+    insertions.sort_by_key(|(at, _)| *at);
+    for (at, piece) in insertions.iter().rev() {
+        grown.splice(*at..*at, piece.chars());
+    }
+
+    Ok(grown.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tree_gen_recursive_tests {
+    use super::*;
+    use std::collections::HashSet;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::sticks::cells_landed_on;
+    use crate::tree_space::TreeSpace;
+    use crate::silhouette::{Silhouette, SilhouetteShape};
+    use crate::confstats::DepthOverride;
+    use crate::{Fvtree, FvtreeString};
+
+    fn collisions(tree_string: &str) -> usize {
+        let cells = cells_landed_on(tree_string, Point {x: 0, y: 0});
+        let unique: HashSet<_> = cells.iter().collect();
+        cells.len() - unique.len()
+    }
+
+    #[test]
+    fn avoiding_collisions() {
+        //With nothing it has to grow, the generator can always stop instead of putting a stick down
+        //on another one, so avoiding collisions avoids every one of them.
+        let mut conf = Config::new();
+        conf.t.min_trunk_height = 0;
+        conf.t.min_sticks = 0;
+        conf.b.min_sticks = 0;
+        conf.b.allow_dead_branches = true;
+        conf.b.min_leaves_in_leaflet = 0;
+        conf.b.min_leaflets = 0;
+
+        let tangled: usize = (0..40)
+            .map(|seed| collisions(&gen(&mut StdRng::seed_from_u64(seed), &mut Stats::new(), &conf)))
+            .sum();
+        assert!(tangled > 0);
+
+        conf.t.avoid_collisions = true;
+        for seed in 0..40 {
+            let tree_string = gen(&mut StdRng::seed_from_u64(seed), &mut Stats::new(), &conf);
+            assert_eq!(collisions(&tree_string), 0, "{}", tree_string);
+            //Still a tree the Config could've grown anyway.
+            if seed < 3 {
+                assert!(TreeSpace::new(&conf).index_of(&tree_string).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn symmetric_trees() {
+        let mut rng = rand::thread_rng();
+        let mut conf = Config::new();
+        conf.t.symmetric = true;
+
+        for _ in 0..20 {
+            let tree_string = gen(&mut rng, &mut Stats::new(), &conf);
+            let cells: HashSet<_> = cells_landed_on(&tree_string, Point {x: 0, y: 0}).into_iter().collect();
+            let mirrored: HashSet<_> = cells.iter().map(|(x, y)| (-x, *y)).collect();
+            assert_eq!(cells, mirrored, "{}", tree_string);
+            //Still a tree the Config could've grown anyway, but long ones take a while to check.
+            if tree_string.len() < 150 {
+                assert!(TreeSpace::new(&conf).index_of(&tree_string).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_sticks() {
+        let mut rng = rand::thread_rng();
+        let mut conf = Config::new();
+        //Blown to the right.
+        conf.t.stick_weights = [1, 1, 10];
+        conf.b.stick_weights = [1, 1, 10];
+        conf.b.leaf_weights = [1, 1, 10, 1, 1, 10, 1, 10];
+        let (mut right, mut left) = (0, 0);
+
+        for i in 0..20 {
+            let tree_string = gen(&mut rng, &mut Stats::new(), &conf);
+            right += tree_string.chars().filter(|c| "/>um".contains(*c)).count();
+            left += tree_string.chars().filter(|c| "\\<tb".contains(*c)).count();
+            //Weights don't change which trees there are.
+            if i < 3 && tree_string.len() < 150 {
+                assert!(TreeSpace::new(&conf).index_of(&tree_string).is_ok());
+            }
+        }
+
+        assert!(left * 3 < right, "{} leaning left, {} leaning right", left, right);
+    }
+
+    #[test]
+    fn zero_weights() {
+        let mut rng = rand::thread_rng();
+        let mut conf = Config::new_fruit_tree();
+        //Nothing to the left.
+        conf.t.stick_weights = [1, 0, 1];
+        conf.b.stick_weights = [1, 0, 1];
+        conf.b.leaf_weights = [1, 0, 1, 1, 0, 1, 0, 1];
+
+        for _ in 0..20 {
+            let tree_string = gen(&mut rng, &mut Stats::new(), &conf);
+            assert!(!tree_string.chars().any(|c| "\\<tb".contains(c)), "{}", tree_string);
+        }
+    }
+
+    //How deep the deepest branch in tree_string goes.
+    fn deepest_branch(tree_string: &str) -> usize {
+        let (mut depth, mut deepest) = (0, 0);
+        for c in tree_string.chars() {
+            if c == BranchIndicator::to_char() {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            else if c == BranchReturn::to_char() {
+                depth -= 1;
+            }
+        }
+        deepest
+    }
+
+    #[test]
+    fn depth_dependent_branches() {
+        let mut rng = rand::thread_rng();
+        let mut conf = Config::new();
+        conf.b.max_sticks = 8;
+        conf.b.taper(DepthOverride {min_sticks: Some(1), max_sticks: Some(1), ..Default::default()}, 3);
+        conf.b.max_branch_depth = Some(3);
+
+        for i in 0..20 {
+            let tree_string = gen(&mut rng, &mut Stats::new(), &conf);
+            assert!(deepest_branch(&tree_string) <= 3, "{}", tree_string);
+            //Still a tree the Config could've grown, but long ones take a while to check.
+            if i < 3 && tree_string.len() < 150 {
+                assert!(TreeSpace::new(&conf).index_of(&tree_string).is_ok());
+            }
+        }
+
+        //Only the branches off of the trunk.
+        conf.b.max_branch_depth = Some(1);
+        for _ in 0..20 {
+            assert!(deepest_branch(&gen(&mut rng, &mut Stats::new(), &conf)) <= 1);
+        }
+    }
+
+    //Whether every char of before is in after, in the same order.
+    fn is_kept_in(before: &str, after: &str) -> bool {
+        let mut after = after.chars();
+        before.chars().all(|c| after.any(|a| a == c))
+    }
+
+    #[test]
+    fn growing_a_tree() {
+        let mut rng = rand::thread_rng();
+        let mut conf = Config::new();
+        conf.t.min_sticks = 0;
+        let mut tree_string = "|||".to_string();
+
+        for day in 0..10 {
+            let grown = grow(&mut rng, &tree_string, &conf).unwrap();
+            assert!(grown.len() >= tree_string.len());
+            assert!(is_kept_in(&tree_string, &grown), "{} isn't in {}", tree_string, grown);
+            //Still a tree the Config could've grown, but long ones take a while to check.
+            if day < 4 && grown.len() < 100 {
+                assert!(TreeSpace::new(&conf).index_of(&grown).is_ok(), "{}", grown);
+            }
+            tree_string = grown;
+        }
+
+        //The trunk's done growing, but branches can still sprout.
+        conf.t.max_trunk_height = 3;
+        let grown = grow(&mut rng, "|||y/o^lr", &conf).unwrap();
+        assert!(grown.starts_with("|||y/o^") && grown.matches('y').count() >= 2, "{}", grown);
+        //Unless the branch is hidden under a leaf spawn, and would have to draw over it.
+        assert_eq!(grow(&mut rng, "|||yo^lr", &conf).unwrap().matches('y').count(), 1);
+        assert!(grow(&mut rng, "||yr", &conf).unwrap().starts_with("||y"));
+        assert!(grow(&mut rng, "||y", &conf).is_err());
+    }
+
+    #[test]
+    fn staying_inside_silhouettes() {
+        let mut rng = rand::thread_rng();
+
+        for shape in [SilhouetteShape::Round, SilhouetteShape::Columnar, SilhouetteShape::Conical, SilhouetteShape::Weeping].iter() {
+            let silhouette = Silhouette::new(*shape);
+            let mut conf = Config::new();
+            conf.t.silhouette = Some(silhouette);
+            let (mut inside, mut outside) = (0, 0);
+
+            for _ in 0..20 {
+                for (x, y) in cells_landed_on(&gen(&mut rng, &mut Stats::new(), &conf), Point {x: 0, y: 0}) {
+                    if silhouette.contains(Point {x, y}) {
+                        inside += 1;
+                    }
+                    else {
+                        outside += 1;
+                    }
+                }
+            }
+
+            assert!(outside * 20 < inside, "{:?}: {} sticks inside, {} outside", shape, inside, outside);
+        }
+    }
+
+    #[test]
+    fn fruit_trees_bear_fruit() {
+        let mut conf = Config::new_fruit_tree();
+        conf.b.fruit_chance = 100;
+        let fvtree_string = FvtreeString::new_recursive_from_config(&conf);
+        assert!(fvtree_string.tree_string().contains('@'));
+        assert!(Fvtree::build(&fvtree_string).is_ok());
+    }
+
+    #[test]
+    fn rendered_trees_fit() {
+        for (width, height) in [(20, 10), (7, 4), (3, 2)].iter() {
+            for symmetric in [false, true].iter() {
+                let mut conf = Config::new_fruit_tree();
+                conf.t.max_width = Some(*width);
+                conf.t.max_height = Some(*height);
+                conf.t.symmetric = *symmetric;
+
+                for _ in 0..20 {
+                    let fvtree_string = FvtreeString::new_recursive_from_config(&conf);
+                    let output = Fvtree::build(&fvtree_string).unwrap().to_string();
+                    assert!(output.lines().count() <= *height, "{}\n{}", fvtree_string, output);
+                    assert!(output.lines().all(|line| line.chars().count() <= *width), "{}\n{}", fvtree_string, output);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn growing_day_by_day() {
+        for conf in [Config::new(), Config::new_fruit_tree(), Config::new_pine(), Config::new_willow()].iter() {
+            for seed in 0..10 {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let mut fvtree_string = FvtreeString::build("v2:||").unwrap();
+                let mut yesterday = Fvtree::build(&fvtree_string).unwrap();
+
+                for _ in 0..8 {
+                    let before = fvtree_string.tree_string();
+                    fvtree_string.grow(&mut rng, conf).unwrap();
+                    assert!(fvtree_string.tree_string().len() >= before.len());
+
+                    //Nothing that was there yesterday has moved or changed, it's only grown.
+                    //Except the top of the trunk, if a branch has sprouted off of it.
+                    let trunk_tip = survey(&before, &mut Stats::new()).unwrap().trunk_tip;
+                    let today = Fvtree::build(&fvtree_string).unwrap();
+                    let cells = today.canvas.cells();
+                    for (point, c) in yesterday.canvas.cells() {
+                        let kept = cells.contains(&(point, c)) || (point == trunk_tip && cells.contains(&(point, 'Y')));
+                        assert!(kept, "{:?} is gone from {}\n{}\n{}", (point, c), fvtree_string, yesterday, today);
+                    }
+                    yesterday = today;
+                }
+                assert!(fvtree_string.tree_string().len() > "||".len());
+            }
+        }
+    }
+}
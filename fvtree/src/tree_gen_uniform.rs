@@ -0,0 +1,99 @@
+use crate::big_uint::BigUint;
+use crate::confstats::Config;
+use crate::tree_space::TreeSpace;
+
+//tree_gen_recursive draws every choice from an even Lottery, so a tree that stops early only had
+//to win a handful of draws, and turns up far more often than one that goes on for a while.
+//This picks a number among every tree of the given length instead, and grows that one,
+//so every tree that long is as likely as any other.
+//
+//Every tree TreeSpace numbers is just as likely, so the parts of a Config that only steer the
+//recursive generator can't be kept to. Rather than quietly drop them, they're turned down.
+pub fn check(conf: &Config) -> Result<(), &'static str> {
+    if conf.t.max_width.is_some() || conf.t.max_height.is_some() {
+        return Err("Uniform trees can't be kept to a max width or height.");
+    }
+    if conf.t.silhouette.is_some() {
+        return Err("Uniform trees can't be kept inside a silhouette.");
+    }
+    if conf.t.symmetric {
+        return Err("Uniform trees can't be made symmetric.");
+    }
+    if conf.t.avoid_collisions {
+        return Err("Uniform trees can't avoid collisions.");
+    }
+
+    Ok(())
+}
+
+//len counts control characters and all, see TreeSpace::count.
+pub fn gen(rng: &mut rand::rngs::ThreadRng, conf: &Config, len: usize) -> Result<String, &'static str> {
+    let mut tree_space = TreeSpace::new(conf);
+
+    let count = tree_space.count(len);
+    if count.is_zero() {
+        return Err("This Config can't grow a tree that long.");
+    }
+
+    let rank = BigUint::random_below(rng, &count);
+    tree_space.tree_of_len_at(len, &rank)
+}
+
+#[cfg(test)]
+mod tree_gen_uniform_tests {
+    use super::*;
+
+    #[test]
+    fn grows_trees_of_the_right_length() {
+        let mut rng = rand::thread_rng();
+        let conf = Config::new_fruit_tree();
+
+        for len in [40, 75].iter() {
+            let tree_string = gen(&mut rng, &conf, *len).unwrap();
+            assert_eq!(tree_string.len(), *len);
+            assert!(TreeSpace::new(&conf).index_of(&tree_string).is_ok());
+        }
+    }
+
+    #[test]
+    fn every_tree_turns_up() {
+        let mut rng = rand::thread_rng();
+        let mut conf = Config::new();
+        conf.t.min_sticks = 0;
+        conf.t.max_trunk_height = 3;
+
+        //Just the 27 trunks 3 sticks tall.
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..500 {
+            seen.insert(gen(&mut rng, &conf, 3).unwrap());
+        }
+        assert_eq!(seen.len(), 27);
+    }
+
+    #[test]
+    fn nothing_that_long() {
+        let mut rng = rand::thread_rng();
+        assert!(gen(&mut rng, &Config::new(), 5).is_err());
+    }
+
+    #[test]
+    fn only_what_uniform_trees_can_keep_to() {
+        assert!(check(&Config::new()).is_ok());
+
+        let mut conf = Config::new();
+        conf.t.max_height = Some(8);
+        assert!(check(&conf).is_err());
+
+        let mut conf = Config::new();
+        conf.t.silhouette = Some("round".parse().unwrap());
+        assert!(check(&conf).is_err());
+
+        let mut conf = Config::new();
+        conf.t.symmetric = true;
+        assert!(check(&conf).is_err());
+
+        let mut conf = Config::new();
+        conf.t.avoid_collisions = true;
+        assert!(check(&conf).is_err());
+    }
+}
@@ -0,0 +1,1241 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::big_uint::BigUint;
+use crate::confstats::{Config, DepthConfig};
+
+use crate::sticks::{Stick, StickCanonical};
+use crate::sticks::{BranchIndicator, BranchReturn};
+use crate::sticks::{LeafSpawn, LeafReturn};
+
+//Every tree string a Config can grow, numbered, like the books in libraryofbabel.
+//
+//Trees are numbered shortest first. Trees of the same length are numbered in the order of the
+//choices tree_gen_recursive makes growing them: stopping first, then each stick, then branching,
+//so each tree keeps its number for as long as its Config doesn't change.
+//
+//To number them, we count how many ways there are to finish each part of a tree (the trunk,
+//a branch, some leaves) given everything tree_gen_recursive keeps track of while growing it,
+//how long the finished part is, and how many sticks it has. Numbering a tree is then adding up
+//the counts of every choice that comes before the one the tree made, and finding the tree with
+//a number is the same thing in reverse.
+//
+//How many sticks a part has matters because the trunk can't stop before the whole tree has
+//t.min_sticks of them. Branch indicators, branch returns, leaf spawns, leaf returns and
+//decorations don't count, just like in confstats::Stats.
+//
+//t.avoid_collisions doesn't come into it: it only ever takes choices away from the generator,
+//so it changes which trees are likely, but not which trees there are.
+//Neither do the weights. Even a weight of 0 only keeps the generator from making a choice,
+//so a tree that needs that choice still has a number, it's just never grown.
+//t.max_width and t.max_height don't either, but they can also stop a part short of its minimums,
+//and a tree like that has no number.
+pub struct TreeSpace<'a> {
+    conf: &'a Config,
+    //Past t.min_sticks, the trunk doesn't care how many sticks there are,
+    //so sticks are counted up to it and no further, or up to one past max_sticks.
+    sticks_counted_to: usize,
+    //Leave out trees with more sticks than this, see new_up_to_sticks().
+    max_sticks: Option<usize>,
+    //Each leaf with each decoration it might have hung off of it.
+    leaf_units: Vec<(char, Option<char>)>,
+    tables: Tables,
+}
+
+//The rest of a part of a tree, and everything that decides what it can look like.
+#[derive(Copy, Clone, Debug)]
+enum Part {
+    //The whole tree, which is all trunk, but the trunk may only stop before t.max_trunk_height
+    //once the tree has t.min_sticks sticks.
+    Tree,
+    //Mirrors gen_trunk. Whether it goes on until t.max_trunk_height, or stops before it.
+    Trunk {height: usize, has_branched: bool, reaches_max_height: bool},
+    //Mirrors gen_branches. sticks is how many sticks the branch has so far,
+    //depth how deep it is, like TreeStats::branch_depth.
+    Branch {sticks: usize, depth: usize},
+    //Mirrors gen_leaves, after the LeafSpawn, at the end of a branch depth deep.
+    Leaves {leaves_in_leaflet: usize, leaflets: usize, depth: usize},
+}
+
+//The fewest and the most sticks a part can have.
+type Sticks = (usize, usize);
+
+//Each choice of how to finish a part is a handful of pieces, one after the other.
+#[derive(Copy, Clone, Debug)]
+enum Piece {
+    Stick(char),
+    //A part, how long it is, and how many sticks it has.
+    Part(Part, usize, Sticks),
+}
+
+//Once a counter in Stats is past every threshold in Config that cares about it, and too far from
+//its maximum to get there before the tree string ends, its exact value doesn't matter anymore.
+//So counters "settle" there, and every value past it is counted as the same one.
+//A counter can have a different maximum at each branch depth, and it only settles when it's too
+//far from every one of them.
+#[derive(Copy, Clone)]
+struct Counter {
+    settled_at: Option<usize>,
+    //The smallest and the largest of its maximums.
+    max: usize,
+    largest_max: usize,
+}
+
+impl Counter {
+    fn new(settles_at: usize, maxes: &[usize], max_len: usize) -> Counter {
+        let max = maxes.iter().copied().min().unwrap_or(0);
+
+        Counter {
+            settled_at: if max > settles_at.saturating_add(max_len) {Some(settles_at)} else {None},
+            max,
+            largest_max: maxes.iter().copied().max().unwrap_or(0),
+        }
+    }
+
+    fn key(&self, value: usize) -> usize {
+        match self.settled_at {
+            Some(settled_at) => value.min(settled_at),
+            None => value,
+        }
+    }
+
+    fn num_keys(&self) -> usize {
+        self.settled_at.unwrap_or(self.largest_max) + 1
+    }
+}
+
+//counts[len][i] is how many ways there are to finish a part with len sticks, for every way it can
+//have started and every number of sticks it can have, see TreeSpace::table_index.
+//cumulative_counts[len][i] adds up counts[len][i] and the ones for fewer sticks before it.
+struct Tables {
+    max_len: usize,
+    height: Counter,
+    branch_sticks: Counter,
+    leaves_in_leaflet: Counter,
+    leaflets: Counter,
+    //How many branch depths BranchConfig tells apart. Any deeper are the same as the deepest.
+    depths: usize,
+    counts: Vec<Vec<BigUint>>,
+    cumulative_counts: Vec<Vec<BigUint>>,
+    //possible_branch_sticks[len][depth - 1] is every number of sticks a branch depth deep with
+    //len sticks can have.
+    //Most numbers are impossible, so this saves trying a lot of choices that can't be finished.
+    possible_branch_sticks: Vec<Vec<Vec<usize>>>,
+}
+
+const BRANCHES: [Stick; 3] = [Stick::UpBranch, Stick::UpLeftBranch, Stick::UpRightBranch];
+const LEAVES: [Stick; 8] = [
+    Stick::UpLeaf, Stick::LeftLeaf, Stick::RightLeaf, Stick::DownLeaf,
+    Stick::UpLeftLeaf, Stick::UpRightLeaf, Stick::DownLeftLeaf, Stick::DownRightLeaf,
+];
+
+//Counts are built this far ahead at first.
+const INITIAL_MAX_LEN: usize = 32;
+
+impl<'a> TreeSpace<'a> {
+    pub fn new(conf: &Config) -> TreeSpace<'_> {
+        TreeSpace::build(conf, None)
+    }
+
+    //Only the trees with up to max_sticks sticks, numbered in their own order, not the same as
+    //new()'s. Errors if there's no end to them, see max_len().
+    pub fn new_up_to_sticks(conf: &Config, max_sticks: usize) -> Result<TreeSpace<'_>, &'static str> {
+        let tree_space = TreeSpace::build(conf, Some(max_sticks));
+
+        match tree_space.max_len() {
+            Some(_) => Ok(tree_space),
+            None => Err("Branches can be bare with this Config, so there's no end to the trees with that few sticks."),
+        }
+    }
+
+    fn build(conf: &Config, max_sticks: Option<usize>) -> TreeSpace<'_> {
+        //The same decorations gen_decoration_or_none might hang off of a leaf, in the same order.
+        let decorations = [
+            (Stick::Fruit, conf.b.fruit_chance),
+            (Stick::Flower, conf.b.flower_chance),
+            (Stick::Blossom, conf.b.blossom_chance),
+        ];
+        let total_chance: usize = decorations.iter().map(|(_, chance)| chance).sum();
+
+        let mut leaf_units = Vec::new();
+        for leaf in LEAVES.iter() {
+            if total_chance < 100 {
+                leaf_units.push((leaf.to_char(), None));
+            }
+            for (decoration, chance) in decorations.iter() {
+                if *chance > 0 {
+                    leaf_units.push((leaf.to_char(), Some(decoration.to_char())));
+                }
+            }
+        }
+
+        let mut tree_space = TreeSpace {
+            conf,
+            sticks_counted_to: max_sticks.map_or(conf.t.min_sticks, |max_sticks| conf.t.min_sticks.max(max_sticks + 1)),
+            max_sticks,
+            leaf_units,
+            tables: Tables {
+                max_len: 0,
+                height: Counter::new(0, &[0], 0),
+                branch_sticks: Counter::new(0, &[0], 0),
+                leaves_in_leaflet: Counter::new(0, &[0], 0),
+                leaflets: Counter::new(0, &[0], 0),
+                depths: 1,
+                counts: Vec::new(),
+                cumulative_counts: Vec::new(),
+                possible_branch_sticks: Vec::new(),
+            },
+        };
+        tree_space.build_tables(INITIAL_MAX_LEN);
+
+        tree_space
+    }
+
+    //How many trees there are with exactly len sticks, control characters and all.
+    pub fn count(&mut self, len: usize) -> BigUint {
+        if self.conf.t.max_sticks == 0 {
+            return if len == 0 {BigUint::one()} else {BigUint::zero()};
+        }
+
+        self.ensure_len(len);
+        self.count_piece(&self.whole_tree(len))
+    }
+
+    //The length of the longest tree, or None if there's no end to them.
+    //
+    //There's no end to them as soon as the trunk can branch, because it can branch as many times
+    //as it likes at the same height. Otherwise, the trunk is all there is.
+    //
+    //With only so many sticks, there's only an end to them when no branch can be bare, with no
+    //sticks or leaves on it. Then every branch has a stick of its own, and the most a stick can
+    //bring along with it is a decoration, and a branch's BranchIndicator, BranchReturn, LeafSpawn
+    //and a LeafReturn for each of its leaflets.
+    pub fn max_len(&self) -> Option<usize> {
+        let longest = self.max_len_with_any_sticks();
+
+        match self.max_sticks {
+            Some(max_sticks) => {
+                let b = &self.conf.b;
+                let depth_confs: Vec<DepthConfig> = (1..=self.tables.depths).map(|depth| b.at_depth(depth)).collect();
+                let leaflets_can_be_bare = b.min_leaves_in_leaflet.min(b.max_leaves_in_leaflet) == 0;
+                let branches_can_be_bare = depth_confs.iter()
+                    .any(|depth_conf| depth_conf.min_sticks.min(depth_conf.max_sticks) == 0)
+                    && (self.conf.t.dead_tree || b.allow_dead_branches || leaflets_can_be_bare);
+                let max_leaflets = depth_confs.iter().map(|depth_conf| depth_conf.max_leaflets).max().unwrap_or(0);
+                let longest_with_sticks = max_sticks * (2 + 3 + max_leaflets);
+
+                match longest {
+                    Some(longest) => Some(longest.min(longest_with_sticks)),
+                    None if branches_can_be_bare => None,
+                    None => Some(longest_with_sticks),
+                }
+            },
+            None => longest,
+        }
+    }
+
+    fn max_len_with_any_sticks(&self) -> Option<usize> {
+        let t = &self.conf.t;
+
+        if t.max_sticks == 0 {
+            return Some(0);
+        }
+
+        let trunk_can_branch = t.min_height_before_trunk_can_branch <= t.height_when_trunk_forced_to_branch
+            && t.min_height_before_trunk_can_branch < t.max_trunk_height;
+
+        if trunk_can_branch && self.branch_can_end() {
+            None
+        }
+        else {
+            Some(t.max_trunk_height)
+        }
+    }
+
+    //The tree numbered index.
+    pub fn tree_at(&mut self, index: &BigUint) -> Result<String, &'static str> {
+        let mut rank = index.clone();
+        let mut len = 0;
+
+        loop {
+            if self.max_len().is_some_and(|max_len| len > max_len) {
+                return Err("There aren't that many trees for this Config.");
+            }
+
+            let count = self.count(len);
+            if rank < count {
+                break;
+            }
+
+            rank = &rank - &count;
+            len += 1;
+        }
+
+        Ok(self.unrank_tree(len, rank))
+    }
+
+    //Every tree up to max_len long, in the order they're numbered in.
+    pub fn trees_up_to(self, max_len: usize) -> Trees<'a> {
+        //Don't bother counting trees longer than the longest.
+        let max_len = match self.max_len() {
+            Some(longest) => max_len.min(longest),
+            None => max_len,
+        };
+
+        Trees {
+            tree_space: self,
+            max_len,
+            len: 0,
+            rank: BigUint::zero(),
+            count: None,
+        }
+    }
+
+    //The tree numbered rank among the trees exactly len long.
+    pub fn tree_of_len_at(&mut self, len: usize, rank: &BigUint) -> Result<String, &'static str> {
+        if *rank >= self.count(len) {
+            return Err("There aren't that many trees of that length for this Config.");
+        }
+
+        Ok(self.unrank_tree(len, rank.clone()))
+    }
+
+    //Same as tree_of_len_at(), for when there's known to be more than rank trees len long.
+    fn unrank_tree(&self, len: usize, rank: BigUint) -> String {
+        let mut tree_string = String::with_capacity(len);
+        if self.conf.t.max_sticks != 0 {
+            self.unrank(self.whole_tree(len), rank, &mut tree_string);
+        }
+
+        tree_string
+    }
+
+    //The number of tree_string, which must be made of current version sticks.
+    pub fn index_of(&mut self, tree_string: &str) -> Result<BigUint, &'static str> {
+        let reading = Reading::new(tree_string);
+        let len = reading.chars.len();
+
+        let rank = if self.conf.t.max_sticks == 0 {
+            if len == 0 {Some(BigUint::zero())} else {None}
+        }
+        else {
+            self.ensure_len(len);
+            self.rank(&reading, self.whole_tree(len), 0)
+        };
+
+        let mut index = match rank {
+            Some(rank) => rank,
+            None => return Err("This Config can't grow that tree string."),
+        };
+
+        for shorter_len in 0..len {
+            index += &self.count(shorter_len);
+        }
+
+        Ok(index)
+    }
+
+    fn whole_tree(&self, len: usize) -> Piece {
+        Piece::Part(Part::Tree, len, (0, self.sticks_counted_to))
+    }
+
+    //Branches can always grow until they run out of sticks, so if the ones off of the trunk can
+    //end there, they can end.
+    fn branch_can_end(&self) -> bool {
+        let t = &self.conf.t;
+        let b = &self.conf.b;
+        let depth_conf = b.at_depth(1);
+
+        let leaves_can_end = b.min_leaves_in_leaflet <= b.max_leaves_in_leaflet
+            && ((b.allow_dead_branches && depth_conf.min_leaflets == 0) || depth_conf.min_leaflets.max(1) <= depth_conf.max_leaflets);
+
+        t.dead_tree || b.allow_dead_branches || leaves_can_end
+    }
+
+    //How many sticks a part might have had before added more were put down after it,
+    //if it has sticks sticks after.
+    fn before_adding(&self, sticks: usize, added: usize) -> Sticks {
+        if sticks == self.sticks_counted_to {
+            (self.sticks_counted_to.saturating_sub(added), self.sticks_counted_to)
+        }
+        else if sticks >= added {
+            (sticks - added, sticks - added)
+        }
+        else {
+            //None at all.
+            (1, 0)
+        }
+    }
+
+    //Grows by doubling, but not so far that a counter that'd settle for len doesn't anymore,
+    //that'd make the tables a lot bigger for nothing.
+    fn ensure_len(&mut self, len: usize) {
+        if len > self.tables.max_len {
+            let stays_settled_up_to = [&self.tables.height, &self.tables.branch_sticks, &self.tables.leaves_in_leaflet, &self.tables.leaflets]
+                .iter()
+                .filter_map(|counter| counter.settled_at.map(|settled_at| counter.max - settled_at - 1))
+                .filter(|&up_to| up_to >= len)
+                .min()
+                .unwrap_or(usize::MAX);
+
+            self.build_tables(len.max(self.tables.max_len * 2).min(stays_settled_up_to));
+        }
+    }
+
+    //Every choice of how to finish part with len sticks, which has sticks sticks, in order.
+    fn for_each_alternative(&self, part: Part, len: usize, sticks: usize, f: &mut dyn FnMut(&[Piece])) {
+        let t = &self.conf.t;
+        let b = &self.conf.b;
+
+        let branch_indicator = Piece::Stick(BranchIndicator::to_char());
+        let branch_return = Piece::Stick(BranchReturn::to_char());
+
+        match part {
+            Part::Tree => {
+                let most = self.max_sticks.unwrap_or(self.sticks_counted_to);
+
+                let trunk = Part::Trunk {height: 0, has_branched: false, reaches_max_height: false};
+                f(&[Piece::Part(trunk, len, (t.min_sticks, most))]);
+
+                let trunk = Part::Trunk {height: 0, has_branched: false, reaches_max_height: true};
+                f(&[Piece::Part(trunk, len, (0, most))]);
+            },
+            Part::Trunk {height, has_branched, reaches_max_height} => {
+                if height >= t.max_trunk_height {
+                    if reaches_max_height && len == 0 && sticks == 0 {
+                        f(&[]);
+                    }
+                    return;
+                }
+
+                if !reaches_max_height && len == 0 && sticks == 0 && height >= t.min_trunk_height {
+                    f(&[]);
+                }
+
+                if (height < t.height_when_trunk_forced_to_branch || has_branched) && len >= 1 {
+                    let rest = Part::Trunk {height: height + 1, has_branched, reaches_max_height};
+                    for stick in BRANCHES.iter() {
+                        f(&[Piece::Stick(stick.to_char()), Piece::Part(rest, len - 1, self.before_adding(sticks, 1))]);
+                    }
+                }
+
+                if height >= t.min_height_before_trunk_can_branch {
+                    let rest = Part::Trunk {height, has_branched: true, reaches_max_height};
+                    for branch_len in 1..len {
+                        for &branch_sticks in self.possible_branch_sticks(branch_len, 1) {
+                            if sticks < self.sticks_counted_to && branch_sticks > sticks {
+                                break;
+                            }
+                            f(&[
+                                branch_indicator,
+                                Piece::Part(Part::Branch {sticks: 0, depth: 1}, branch_len, (branch_sticks, branch_sticks)),
+                                Piece::Part(rest, len - 1 - branch_len, self.before_adding(sticks, branch_sticks)),
+                            ]);
+                        }
+                    }
+                }
+            },
+            Part::Branch {sticks: branch_sticks_so_far, depth} => {
+                let depth_conf = b.at_depth(depth);
+
+                //Ran out of sticks.
+                if branch_sticks_so_far >= depth_conf.max_sticks {
+                    if !b.allow_dead_branches && !t.dead_tree {
+                        self.leaves_then_return(len, sticks, depth, f);
+                    }
+                    else if len == 1 && sticks == 0 {
+                        f(&[branch_return]);
+                    }
+                    return;
+                }
+
+                if branch_sticks_so_far >= depth_conf.min_sticks {
+                    if !t.dead_tree {
+                        self.leaves_then_return(len, sticks, depth, f);
+                    }
+                    //A dead tree stopping and a dead branch look the same.
+                    if (t.dead_tree || b.allow_dead_branches) && len == 1 && sticks == 0 {
+                        f(&[branch_return]);
+                    }
+                }
+
+                if len >= 1 {
+                    let rest = Part::Branch {sticks: branch_sticks_so_far + 1, depth};
+                    for stick in BRANCHES.iter() {
+                        f(&[Piece::Stick(stick.to_char()), Piece::Part(rest, len - 1, self.before_adding(sticks, 1))]);
+                    }
+                }
+
+                if branch_sticks_so_far >= b.min_sticks_before_branch && depth_conf.can_branch {
+                    //Deeper than the deepest depth BranchConfig tells apart is the same as it.
+                    let branch_depth = (depth + 1).min(self.tables.depths);
+                    for branch_len in 1..len {
+                        for &branch_sticks in self.possible_branch_sticks(branch_len, branch_depth) {
+                            if sticks < self.sticks_counted_to && branch_sticks > sticks {
+                                break;
+                            }
+                            f(&[
+                                branch_indicator,
+                                Piece::Part(Part::Branch {sticks: 0, depth: branch_depth}, branch_len, (branch_sticks, branch_sticks)),
+                                Piece::Part(part, len - 1 - branch_len, self.before_adding(sticks, branch_sticks)),
+                            ]);
+                        }
+                    }
+                }
+            },
+            Part::Leaves {leaves_in_leaflet, leaflets, depth} => {
+                let depth_conf = b.at_depth(depth);
+
+                if len == 0 && sticks == 0
+                && (leaflets > 0 || b.allow_dead_branches)
+                && leaves_in_leaflet >= b.min_leaves_in_leaflet
+                && leaflets >= depth_conf.min_leaflets {
+                    f(&[]);
+                }
+
+                if leaves_in_leaflet < b.max_leaves_in_leaflet {
+                    let rest = Part::Leaves {leaves_in_leaflet: leaves_in_leaflet + 1, leaflets, depth};
+                    for &(leaf, decoration) in self.leaf_units.iter() {
+                        let unit_len = if decoration.is_some() {2} else {1};
+                        if len < unit_len {
+                            continue;
+                        }
+
+                        let rest = Piece::Part(rest, len - unit_len, self.before_adding(sticks, 1));
+                        match decoration {
+                            Some(decoration) => f(&[Piece::Stick(leaf), Piece::Stick(decoration), rest]),
+                            None => f(&[Piece::Stick(leaf), rest]),
+                        }
+                    }
+                }
+
+                if leaves_in_leaflet >= b.min_leaves_in_leaflet && leaflets < depth_conf.max_leaflets && len >= 1 {
+                    let rest = Part::Leaves {leaves_in_leaflet: 0, leaflets: leaflets + 1, depth};
+                    f(&[Piece::Stick(LeafReturn::to_char()), Piece::Part(rest, len - 1, (sticks, sticks))]);
+                }
+            },
+        }
+    }
+
+    fn leaves_then_return(&self, len: usize, sticks: usize, depth: usize, f: &mut dyn FnMut(&[Piece])) {
+        if len >= 2 {
+            f(&[
+                Piece::Stick(LeafSpawn::to_char()),
+                Piece::Part(Part::Leaves {leaves_in_leaflet: 0, leaflets: 0, depth}, len - 2, (sticks, sticks)),
+                Piece::Stick(BranchReturn::to_char()),
+            ]);
+        }
+    }
+
+    //Where a part's counts start in each row of the tables, one after the other for each
+    //number of sticks it can have.
+    fn table_index(&self, part: Part) -> usize {
+        let tables = &self.tables;
+
+        let i = match part {
+            Part::Tree => panic!("The whole tree isn't kept in the tables."),
+            Part::Trunk {height, has_branched, reaches_max_height} => {
+                (tables.height.key(height) * 2 + has_branched as usize) * 2 + reaches_max_height as usize
+            },
+            Part::Branch {sticks, depth} => {
+                tables.height.num_keys() * 4
+                    + (depth - 1) * tables.branch_sticks.num_keys()
+                    + tables.branch_sticks.key(sticks)
+            },
+            Part::Leaves {leaves_in_leaflet, leaflets, depth} => {
+                tables.height.num_keys() * 4 + tables.depths * tables.branch_sticks.num_keys()
+                    + ((depth - 1) * tables.leaves_in_leaflet.num_keys() + tables.leaves_in_leaflet.key(leaves_in_leaflet))
+                        * tables.leaflets.num_keys()
+                    + tables.leaflets.key(leaflets)
+            },
+        };
+
+        i * (self.sticks_counted_to + 1)
+    }
+
+    fn counted(&self, part: Part, len: usize, (fewest, most): Sticks) -> Cow<'_, BigUint> {
+        if let Part::Tree = part {
+            return Cow::Owned(self.count_part(part, len, 0));
+        }
+        if fewest > most {
+            return Cow::Owned(BigUint::zero());
+        }
+
+        let i = self.table_index(part);
+        if fewest == most {
+            Cow::Borrowed(&self.tables.counts[len][i + most])
+        }
+        else if fewest == 0 {
+            Cow::Borrowed(&self.tables.cumulative_counts[len][i + most])
+        }
+        else {
+            let cumulative_counts = &self.tables.cumulative_counts[len];
+            Cow::Owned(&cumulative_counts[i + most] - &cumulative_counts[i + fewest - 1])
+        }
+    }
+
+    fn is_possible(&self, part: Part, len: usize, (fewest, most): Sticks) -> bool {
+        if fewest > most {
+            return false;
+        }
+        if fewest == 0 || fewest == most {
+            return !self.counted(part, len, (fewest, most)).is_zero();
+        }
+
+        let i = self.table_index(part);
+        let cumulative_counts = &self.tables.cumulative_counts[len];
+        cumulative_counts[i + most] != cumulative_counts[i + fewest - 1]
+    }
+
+    fn count_piece(&self, piece: &Piece) -> BigUint {
+        match *piece {
+            Piece::Stick(_) => BigUint::one(),
+            Piece::Part(part, len, sticks) => self.counted(part, len, sticks).into_owned(),
+        }
+    }
+
+    fn count_alternative(&self, pieces: &[Piece]) -> BigUint {
+        for piece in pieces {
+            if let Piece::Part(part, len, sticks) = *piece {
+                if !self.is_possible(part, len, sticks) {
+                    return BigUint::zero();
+                }
+            }
+        }
+
+        let mut count = BigUint::one();
+        for piece in pieces {
+            if let Piece::Part(part, len, sticks) = *piece {
+                count = &count * &self.counted(part, len, sticks);
+            }
+        }
+
+        count
+    }
+
+    fn count_part(&self, part: Part, len: usize, sticks: usize) -> BigUint {
+        let mut count = BigUint::zero();
+        self.for_each_alternative(part, len, sticks, &mut |pieces| {
+            count += &self.count_alternative(pieces);
+        });
+        count
+    }
+
+    //Every way a part can have started, in the same order as table_index.
+    fn all_starts(&self) -> Vec<Part> {
+        let tables = &self.tables;
+        let mut starts = Vec::new();
+
+        for height in 0..tables.height.num_keys() {
+            for &has_branched in [false, true].iter() {
+                for &reaches_max_height in [false, true].iter() {
+                    starts.push(Part::Trunk {height, has_branched, reaches_max_height});
+                }
+            }
+        }
+        for depth in 1..=tables.depths {
+            for sticks in 0..tables.branch_sticks.num_keys() {
+                starts.push(Part::Branch {sticks, depth});
+            }
+        }
+        for depth in 1..=tables.depths {
+            for leaves_in_leaflet in 0..tables.leaves_in_leaflet.num_keys() {
+                for leaflets in 0..tables.leaflets.num_keys() {
+                    starts.push(Part::Leaves {leaves_in_leaflet, leaflets, depth});
+                }
+            }
+        }
+
+        starts
+    }
+
+    //Every choice only ever leads to shorter parts, so the tables fill in shortest first.
+    fn build_tables(&mut self, max_len: usize) {
+        let t = &self.conf.t;
+        let b = &self.conf.b;
+
+        //Past the last depth override, and past max_branch_depth, every depth is the same.
+        let depths = b.depth_overrides.len().max(b.max_branch_depth.unwrap_or(0)).max(1);
+        let depth_confs: Vec<DepthConfig> = (1..=depths).map(|depth| b.at_depth(depth)).collect();
+
+        let trunk_settles_at = t.min_trunk_height
+            .max(t.min_height_before_trunk_can_branch)
+            .max(t.height_when_trunk_forced_to_branch);
+        let branch_settles_at = depth_confs.iter()
+            .map(|depth_conf| depth_conf.min_sticks.max(b.min_sticks_before_branch))
+            .max()
+            .unwrap_or(0);
+        let leaflets_settle_at = depth_confs.iter()
+            .map(|depth_conf| depth_conf.min_leaflets.max(1))
+            .max()
+            .unwrap_or(0);
+        let max_branch_sticks: Vec<usize> = depth_confs.iter().map(|depth_conf| depth_conf.max_sticks).collect();
+        let max_leaflets: Vec<usize> = depth_confs.iter().map(|depth_conf| depth_conf.max_leaflets).collect();
+
+        self.tables = Tables {
+            max_len,
+            height: Counter::new(trunk_settles_at, &[t.max_trunk_height], max_len),
+            branch_sticks: Counter::new(branch_settles_at, &max_branch_sticks, max_len),
+            leaves_in_leaflet: Counter::new(b.min_leaves_in_leaflet, &[b.max_leaves_in_leaflet], max_len),
+            leaflets: Counter::new(leaflets_settle_at, &max_leaflets, max_len),
+            depths,
+            counts: Vec::with_capacity(max_len + 1),
+            cumulative_counts: Vec::with_capacity(max_len + 1),
+            possible_branch_sticks: Vec::with_capacity(max_len + 1),
+        };
+
+        let starts = self.all_starts();
+
+        for len in 0..=max_len {
+            let mut counts = Vec::with_capacity(starts.len() * (self.sticks_counted_to + 1));
+            let mut cumulative_counts = Vec::with_capacity(counts.capacity());
+
+            for &part in starts.iter() {
+                let mut cumulative_count = BigUint::zero();
+                for sticks in 0..=self.sticks_counted_to {
+                    let count = self.count_part(part, len, sticks);
+                    cumulative_count += &count;
+                    counts.push(count);
+                    cumulative_counts.push(cumulative_count.clone());
+                }
+            }
+
+            self.tables.counts.push(counts);
+            self.tables.cumulative_counts.push(cumulative_counts);
+
+            let possible_branch_sticks = (1..=depths)
+                .map(|depth| {
+                    (0..=self.sticks_counted_to)
+                        .filter(|&sticks| self.is_possible(Part::Branch {sticks: 0, depth}, len, (sticks, sticks)))
+                        .collect()
+                })
+                .collect();
+            self.tables.possible_branch_sticks.push(possible_branch_sticks);
+        }
+    }
+
+    fn possible_branch_sticks(&self, len: usize, depth: usize) -> &[usize] {
+        &self.tables.possible_branch_sticks[len][depth - 1]
+    }
+
+    //Finds the alternative rank falls in, and rank within it.
+    fn choose_alternative(&self, part: Part, len: usize, sticks: usize, mut rank: BigUint) -> (Vec<Piece>, BigUint) {
+        let mut chosen = None;
+
+        self.for_each_alternative(part, len, sticks, &mut |pieces| {
+            if chosen.is_some() {
+                return;
+            }
+
+            let count = self.count_alternative(pieces);
+            if rank < count {
+                chosen = Some(pieces.to_vec());
+            }
+            else {
+                rank = &rank - &count;
+            }
+        });
+
+        (chosen.expect("rank should be less than the count of the part."), rank)
+    }
+
+    //The rank of each piece of an alternative, the last piece being the least significant.
+    fn split_rank(&self, pieces: &[Piece], mut rank: BigUint) -> Vec<BigUint> {
+        let mut ranks = vec![BigUint::zero(); pieces.len()];
+
+        for (i, piece) in pieces.iter().enumerate().rev() {
+            let (quotient, remainder) = rank.div_rem(&self.count_piece(piece));
+            ranks[i] = remainder;
+            rank = quotient;
+        }
+
+        ranks
+    }
+
+    //A range of sticks only ever comes up for parts with as many sticks as the trunk cares
+    //about, or more, and then the exact number is picked as if it was another choice.
+    fn choose_sticks(&self, part: Part, len: usize, (fewest, most): Sticks, mut rank: BigUint) -> (usize, BigUint) {
+        for sticks in fewest..most {
+            let count = self.counted(part, len, (sticks, sticks));
+            if rank < *count {
+                return (sticks, rank);
+            }
+            rank = &rank - &count;
+        }
+
+        (most, rank)
+    }
+
+    //The last piece of an alternative is usually the rest of the same part, e.g. the trunk after
+    //a stick, so it's looped over rather than recursed into, otherwise a tall enough trunk would
+    //overflow the stack.
+    fn unrank(&self, mut piece: Piece, mut rank: BigUint, tree_string: &mut String) {
+        while let Piece::Part(part, len, sticks) = piece {
+            let (sticks, rank_in_part) = match part {
+                Part::Tree => (0, rank),
+                _ => self.choose_sticks(part, len, sticks, rank),
+            };
+            let (pieces, rank_in_alternative) = self.choose_alternative(part, len, sticks, rank_in_part);
+            let mut ranks = self.split_rank(&pieces, rank_in_alternative);
+
+            let (last, last_rank) = match (pieces.last(), ranks.pop()) {
+                (Some(last), Some(last_rank)) => (*last, last_rank),
+                _ => return,
+            };
+
+            for (piece, rank) in pieces.iter().zip(ranks) {
+                match piece {
+                    Piece::Stick(c) => tree_string.push(*c),
+                    Piece::Part(..) => self.unrank(*piece, rank, tree_string),
+                }
+            }
+
+            piece = last;
+            rank = last_rank;
+        }
+
+        if let Piece::Stick(c) = piece {
+            tree_string.push(c);
+        }
+    }
+
+    //Whether the alternative could be the one the tree string took at start,
+    //without looking any deeper than the sticks it puts down and the lengths of its parts.
+    //At most one alternative can be.
+    fn could_be(&self, reading: &Reading, pieces: &[Piece], start: usize) -> bool {
+        let mut i = start;
+
+        for piece in pieces {
+            match *piece {
+                Piece::Stick(c) => {
+                    if reading.chars.get(i) != Some(&c) {
+                        return false;
+                    }
+                    i += 1;
+                },
+                Piece::Part(part, len, (fewest, most)) => {
+                    //No part starts with a decoration, they only come right after a leaf.
+                    if len > 0 && reading.is_decoration(i) {
+                        return false;
+                    }
+
+                    let sticks = reading.sticks_between(i, i + len).min(self.sticks_counted_to);
+                    if sticks < fewest || sticks > most {
+                        return false;
+                    }
+
+                    match part {
+                        Part::Trunk {height, reaches_max_height, ..} => {
+                            let final_height = height + reading.trunk_sticks_between(i, i + len);
+                            if (final_height >= self.conf.t.max_trunk_height) != reaches_max_height {
+                                return false;
+                            }
+                        },
+                        Part::Branch {..} => {
+                            if len == 0 || reading.branch_end[i] != Some(i + len - 1) {
+                                return false;
+                            }
+                        },
+                        Part::Tree | Part::Leaves {..} => (),
+                    }
+
+                    i += len;
+                },
+            }
+        }
+
+        true
+    }
+
+    //Like unrank, loops over the last piece, and recurses into the rest.
+    fn rank(&self, reading: &Reading, mut piece: Piece, mut start: usize) -> Option<BigUint> {
+        let mut rank = BigUint::zero();
+
+        while let Piece::Part(part, len, (fewest, _)) = piece {
+            let sticks = match part {
+                Part::Tree => 0,
+                _ => reading.sticks_between(start, start + len).min(self.sticks_counted_to),
+            };
+            if sticks > fewest {
+                rank += &self.counted(part, len, (fewest, sticks - 1));
+            }
+
+            let mut offset = BigUint::zero();
+            let mut found = None;
+
+            self.for_each_alternative(part, len, sticks, &mut |pieces| {
+                if found.is_some() {
+                    return;
+                }
+
+                if self.could_be(reading, pieces, start) {
+                    found = Some(pieces.to_vec());
+                }
+                else {
+                    offset += &self.count_alternative(pieces);
+                }
+            });
+
+            let pieces = found?;
+            let (last, rest) = match pieces.split_last() {
+                Some((last, rest)) => (*last, rest),
+                None => return Some(rank),
+            };
+
+            //The rank within the alternative, leaving room for the last piece's, which comes after.
+            let mut rank_in_alternative = BigUint::zero();
+            for piece in rest {
+                rank_in_alternative = &rank_in_alternative * &self.count_piece(piece);
+
+                match *piece {
+                    Piece::Stick(_) => start += 1,
+                    Piece::Part(_, len, _) => {
+                        rank_in_alternative += &self.rank(reading, *piece, start)?;
+                        start += len;
+                    },
+                }
+            }
+            rank_in_alternative = &rank_in_alternative * &self.count_piece(&last);
+            rank += &offset;
+            rank += &rank_in_alternative;
+
+            piece = last;
+        }
+
+        Some(rank)
+    }
+}
+
+//Every tree up to a length, in the order they're numbered in.
+//Goes through the trees of each length in turn, rather than finding each tree from scratch
+//with tree_at().
+pub struct Trees<'a> {
+    tree_space: TreeSpace<'a>,
+    max_len: usize,
+    len: usize,
+    rank: BigUint,
+    //How many trees are len long, once it's been counted.
+    count: Option<BigUint>,
+}
+
+impl Iterator for Trees<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        while self.len <= self.max_len {
+            let tree_space = &mut self.tree_space;
+            let len = self.len;
+            let count = self.count.get_or_insert_with(|| tree_space.count(len));
+
+            if self.rank < *count {
+                let tree_string = self.tree_space.unrank_tree(self.len, self.rank.clone());
+                self.rank = self.rank.add_small(1);
+                return Some(tree_string);
+            }
+
+            self.len += 1;
+            self.rank = BigUint::zero();
+            self.count = None;
+        }
+
+        None
+    }
+}
+
+//A tree string laid out to be ranked.
+struct Reading {
+    chars: Vec<char>,
+    //sticks_before[i] is how many sticks come before chars[i].
+    sticks_before: Vec<usize>,
+    //The same, but only the ones in the trunk.
+    trunk_sticks_before: Vec<usize>,
+    //branch_end[i] is where the branch that chars[i] is in ends, at its BranchReturn.
+    branch_end: Vec<Option<usize>>,
+}
+
+impl Reading {
+    fn new(tree_string: &str) -> Reading {
+        let chars: Vec<char> = tree_string.chars().collect();
+
+        //How deep in branches chars[i] is.
+        let mut depths = Vec::with_capacity(chars.len());
+        let mut depth: i64 = 0;
+        for &c in chars.iter() {
+            depths.push(depth);
+            if c == BranchIndicator::to_char() {
+                depth += 1;
+            }
+            else if c == BranchReturn::to_char() {
+                depth -= 1;
+            }
+        }
+
+        let mut sticks_before = Vec::with_capacity(chars.len() + 1);
+        let mut trunk_sticks_before = Vec::with_capacity(chars.len() + 1);
+        let (mut sticks, mut trunk_sticks) = (0, 0);
+        sticks_before.push(sticks);
+        trunk_sticks_before.push(trunk_sticks);
+        for (&c, &depth) in chars.iter().zip(depths.iter()) {
+            if let Ok(stick) = Stick::try_from(c) {
+                if !stick.is_control_char() && !stick.is_decoration() {
+                    sticks += 1;
+                    if depth == 0 {
+                        trunk_sticks += 1;
+                    }
+                }
+            }
+            sticks_before.push(sticks);
+            trunk_sticks_before.push(trunk_sticks);
+        }
+
+        let mut branch_end = vec![None; chars.len()];
+        let mut next_return: HashMap<i64, usize> = HashMap::new();
+        for i in (0..chars.len()).rev() {
+            if chars[i] == BranchReturn::to_char() {
+                next_return.insert(depths[i], i);
+            }
+            branch_end[i] = next_return.get(&depths[i]).copied();
+        }
+
+        Reading {chars, sticks_before, trunk_sticks_before, branch_end}
+    }
+
+    fn sticks_between(&self, start: usize, end: usize) -> usize {
+        self.sticks_before[end] - self.sticks_before[start]
+    }
+
+    fn trunk_sticks_between(&self, start: usize, end: usize) -> usize {
+        self.trunk_sticks_before[end] - self.trunk_sticks_before[start]
+    }
+
+    fn is_decoration(&self, i: usize) -> bool {
+        match self.chars.get(i).map(|&c| Stick::try_from(c)) {
+            Some(Ok(stick)) => stick.is_decoration(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tree_space_tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::{Fvtree, FvtreeString};
+
+    use crate::confstats::{Stats, DepthOverride};
+    use crate::tree_gen_recursive;
+
+    fn small_config() -> Config {
+        let mut conf = Config::new();
+        conf.t.min_trunk_height = 1;
+        conf.t.max_trunk_height = 4;
+        conf.t.min_height_before_trunk_can_branch = 1;
+        conf.t.height_when_trunk_forced_to_branch = 2;
+        conf.t.min_sticks = 3;
+        conf.b.min_sticks = 1;
+        conf.b.max_sticks = 2;
+        conf.b.min_sticks_before_branch = 1;
+        conf.b.min_leaves_in_leaflet = 1;
+        conf.b.max_leaves_in_leaflet = 2;
+        conf.b.min_leaflets = 0;
+        conf.b.max_leaflets = 1;
+        conf.b.allow_dead_branches = true;
+        conf.b.fruit_chance = 10;
+        conf
+    }
+
+    fn generate(conf: &Config) -> String {
+        let mut rng = rand::thread_rng();
+        tree_gen_recursive::gen(&mut rng, &mut Stats::new(), conf)
+    }
+
+    //Long trees take a while to count in a debug build.
+    fn generate_short(conf: &Config) -> String {
+        loop {
+            let tree_string = generate(conf);
+            if tree_string.len() <= 100 {
+                return tree_string;
+            }
+        }
+    }
+
+    #[test]
+    fn numbers_every_tree_once() {
+        let conf = small_config();
+        let mut tree_space = TreeSpace::new(&conf);
+
+        let mut seen = HashSet::new();
+        let mut index = BigUint::zero();
+        for len in 0..=7 {
+            let count = tree_space.count(len).to_u128().unwrap();
+            for _ in 0..count {
+                let tree_string = tree_space.tree_at(&index).unwrap();
+                assert_eq!(tree_string.len(), len);
+                assert_eq!(tree_space.index_of(&tree_string), Ok(index.clone()));
+                assert!(seen.insert(tree_string));
+                index = index.add_small(1);
+            }
+        }
+
+        //Everything the generator grows that's short enough has been numbered already.
+        for _ in 0..2000 {
+            let tree_string = generate(&conf);
+            if tree_string.len() <= 7 {
+                assert!(seen.contains(&tree_string), "{} wasn't numbered", tree_string);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_trees_round_trip() {
+        for conf in [Config::new(), Config::new_fruit_tree(), small_config()].iter() {
+            let mut tree_space = TreeSpace::new(conf);
+
+            for _ in 0..5 {
+                let tree_string = generate_short(conf);
+                let index = tree_space.index_of(&tree_string).unwrap();
+                assert_eq!(tree_space.tree_at(&index), Ok(tree_string));
+            }
+        }
+    }
+
+    #[test]
+    fn depth_dependent_branches() {
+        let mut conf = small_config();
+        conf.b.max_sticks = 3;
+        conf.b.depth_overrides = vec![
+            DepthOverride {min_sticks: Some(2), ..Default::default()},
+            DepthOverride {max_sticks: Some(1), max_leaflets: Some(0), ..Default::default()},
+        ];
+        conf.b.max_branch_depth = Some(3);
+        let mut tree_space = TreeSpace::new(&conf);
+
+        for index in 0..300u64 {
+            let tree_string = tree_space.tree_at(&BigUint::from(index)).unwrap();
+            assert_eq!(tree_space.index_of(&tree_string), Ok(BigUint::from(index)));
+        }
+        for _ in 0..5 {
+            let tree_string = generate_short(&conf);
+            let index = tree_space.index_of(&tree_string).unwrap();
+            assert_eq!(tree_space.tree_at(&index), Ok(tree_string));
+        }
+
+        //A branch off of the trunk with one stick is too short now, and one off of that with two
+        //is too long.
+        assert!(tree_space.index_of("||y||r").is_ok());
+        assert!(tree_space.index_of("||y|r").is_err());
+        assert!(tree_space.index_of("||y||y|rr").is_ok());
+        assert!(tree_space.index_of("||y||y||rr").is_err());
+    }
+
+    #[test]
+    fn not_every_tree_string() {
+        let conf = Config::new();
+        let mut tree_space = TreeSpace::new(&conf);
+
+        assert!(tree_space.index_of("").is_err());
+        assert!(tree_space.index_of("|||").is_err());
+        assert!(tree_space.index_of("||y|r@").is_err());
+    }
+
+    #[test]
+    fn trees_come_in_order() {
+        let conf = small_config();
+        let mut tree_space = TreeSpace::new(&conf);
+
+        let trees: Vec<String> = TreeSpace::new(&conf).trees_up_to(6).collect();
+        let total: u128 = (0..=6).map(|len| tree_space.count(len).to_u128().unwrap()).sum();
+        assert_eq!(trees.len() as u128, total);
+
+        for (i, tree_string) in trees.iter().enumerate() {
+            assert_eq!(tree_space.tree_at(&BigUint::from(i as u64)).as_ref(), Ok(tree_string));
+        }
+    }
+
+    #[test]
+    fn trees_up_to_sticks() {
+        let conf = small_config();
+        let sticks = |tree_string: &str| Reading::new(tree_string).sticks_between(0, tree_string.len());
+
+        let mut tree_space = TreeSpace::new_up_to_sticks(&conf, 4).unwrap();
+        let trees: Vec<String> = TreeSpace::new_up_to_sticks(&conf, 4).unwrap().trees_up_to(usize::MAX).collect();
+        assert!(trees.iter().all(|tree_string| sticks(tree_string) <= 4));
+        for (i, tree_string) in trees.iter().enumerate() {
+            assert_eq!(tree_space.tree_at(&BigUint::from(i as u64)).as_ref(), Ok(tree_string));
+        }
+
+        //The same trees as there are with any number of sticks, once the rest are left out.
+        let short: HashSet<&String> = trees.iter().filter(|tree_string| tree_string.len() <= 8).collect();
+        let all_short: Vec<String> = TreeSpace::new(&conf).trees_up_to(8).collect();
+        assert_eq!(short, all_short.iter().filter(|tree_string| sticks(tree_string) <= 4).collect());
+        assert!(!short.is_empty());
+
+        //Bare branches, as many as you like.
+        let mut conf = small_config();
+        conf.b.min_sticks = 0;
+        assert!(TreeSpace::new_up_to_sticks(&conf, 4).is_err());
+    }
+
+    #[test]
+    fn finite_tree_spaces_run_out() {
+        let mut conf = Config::new();
+        conf.t.min_height_before_trunk_can_branch = 5;
+        conf.t.height_when_trunk_forced_to_branch = 5;
+        conf.t.max_trunk_height = 5;
+        conf.t.min_sticks = 0;
+        let mut tree_space = TreeSpace::new(&conf);
+
+        assert_eq!(tree_space.max_len(), Some(5));
+        //Trunks of height 3, 4 and 5.
+        let total = 27 + 81 + 243;
+        assert!(tree_space.tree_at(&BigUint::from(total as u64 - 1)).is_ok());
+        assert!(tree_space.tree_at(&BigUint::from(total as u64)).is_err());
+        assert_eq!(tree_space.trees_up_to(100).count(), total);
+    }
+
+    #[test]
+    fn index_round_trip() {
+        let conf = Config::new();
+        for i in [0u64, 1, 12345].iter() {
+            let index = BigUint::from(*i);
+            let fvtree_string = FvtreeString::from_index(&index, &conf).unwrap();
+            assert_eq!(fvtree_string.index(&conf), Ok(index));
+            assert!(Fvtree::build(&fvtree_string).is_ok());
+        }
+    }
+
+    #[test]
+    fn enumerate_matches_count() {
+        //Otherwise there's nothing this short.
+        let mut conf = Config::new();
+        conf.t.min_sticks = 0;
+        let total = FvtreeString::count_up_to(4, &conf).iter().fold(BigUint::zero(), |total, count| &total + count);
+        let mut enumerated = 0u64;
+
+        for fvtree_string in FvtreeString::enumerate(4, &conf) {
+            assert_eq!(fvtree_string.index(&conf), Ok(BigUint::from(enumerated)));
+            enumerated += 1;
+        }
+        assert_eq!(BigUint::from(enumerated), total);
+        assert!(enumerated > 0);
+    }
+
+    #[test]
+    fn enumerate_up_to_sticks() {
+        let mut conf = Config::new();
+        conf.t.min_sticks = 0;
+        let total = FvtreeString::count_up_to_sticks(4, &conf).unwrap().iter().fold(BigUint::zero(), |total, count| &total + count);
+        let mut enumerated = 0u64;
+
+        for fvtree_string in FvtreeString::enumerate_up_to_sticks(4, &conf).unwrap() {
+            let sticks = fvtree_string.sticks().iter()
+                .filter(|stick| !stick.is_control_char() && !stick.is_decoration())
+                .count();
+            assert!(sticks <= 4);
+            enumerated += 1;
+        }
+        assert_eq!(BigUint::from(enumerated), total);
+        assert!(enumerated > 0);
+    }
+}
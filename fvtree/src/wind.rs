@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+
+use common::Point;
+
+use crate::Fvtree;
+
+//Wind blowing through a tree that's already been built, a frame at a time.
+//
+//The higher up a cell is, the further it sways, so the trunk stays put while the top of the tree
+//goes back and forth. Now and then a leaf comes loose and drifts down to the ground line with the
+//wind, lies there a while, then grows back where it came from, so the tree never ends up bare.
+pub struct WindConfig {
+    //How many cells the top of the tree sways either way.
+    pub strength: usize,
+    //How many frames it takes to sway there and back.
+    pub period: usize,
+    //Chance, out of 100, that a leaf comes loose each frame.
+    pub leaf_fall_chance: usize,
+}
+
+impl WindConfig {
+    pub fn new() -> WindConfig {
+        WindConfig {
+            strength: 1,
+            period: 16,
+            leaf_fall_chance: 10,
+        }
+    }
+}
+
+impl Default for WindConfig {
+    fn default() -> WindConfig {
+        WindConfig::new()
+    }
+}
+
+//Leaves, in every season, and what hangs off of them.
+const LEAF_GLYPHS: &str = "\"'%*@";
+
+//How many sways a fallen leaf lies on the ground before it grows back.
+const SWAYS_ON_GROUND: usize = 4;
+
+struct LooseLeaf {
+    c: char,
+    //Where it was on the tree.
+    home: Point,
+    at: Point,
+    //The frame it reached the ground, once it has.
+    landed: Option<usize>,
+}
+
+//The frames of Fvtree::sway, which go on forever.
+pub struct Sway<R: Rng> {
+    strength: usize,
+    period: usize,
+    leaf_fall_chance: usize,
+    //Every cell of the tree that's still on it.
+    cells: Vec<(Point, char)>,
+    loose_leaves: Vec<LooseLeaf>,
+    //The corners of the tree, swaying as far as it goes either way.
+    bounds: Option<(Point, Point)>,
+    top: i32,
+    frame: usize,
+    rng: R,
+}
+
+impl<R: Rng> Sway<R> {
+    pub fn new(fvtree: &Fvtree, conf: &WindConfig, rng: R) -> Sway<R> {
+        let strength = conf.strength as i32;
+        let bounds = fvtree.canvas.bounds().map(|(min_corner, max_corner)| {
+            (Point {x: min_corner.x - strength, y: min_corner.y}, Point {x: max_corner.x + strength, y: max_corner.y})
+        });
+
+        Sway {
+            strength: conf.strength,
+            period: conf.period.max(1),
+            leaf_fall_chance: conf.leaf_fall_chance,
+            cells: fvtree.canvas.cells(),
+            loose_leaves: Vec::new(),
+            top: bounds.map_or(0, |(_, max_corner)| max_corner.y),
+            bounds,
+            frame: 0,
+            rng,
+        }
+    }
+
+    //How far along the sway is, from -1 all the way left to 1 all the way right.
+    fn phase(&self) -> f64 {
+        (self.frame as f64 * 2.0 * std::f64::consts::PI / self.period as f64).sin()
+    }
+
+    //How far a cell at height y sways this frame. Only what's above the root moves,
+    //and the top most of all.
+    fn sway_at(&self, y: i32) -> i32 {
+        if y <= 0 || self.top <= 0 {
+            return 0;
+        }
+
+        let height = y as f64 / self.top as f64;
+        (self.strength as f64 * height * height * self.phase()).round() as i32
+    }
+
+    fn loosen_a_leaf(&mut self) {
+        let leaves: Vec<usize> = (0..self.cells.len())
+            .filter(|&i| LEAF_GLYPHS.contains(self.cells[i].1))
+            .collect();
+        if leaves.is_empty() {
+            return;
+        }
+
+        let (home, c) = self.cells.remove(leaves[self.rng.gen_range(0..leaves.len())]);
+        let at = Point {x: home.x + self.sway_at(home.y), y: home.y};
+        self.loose_leaves.push(LooseLeaf {c, home, at, landed: None});
+    }
+
+    //Falling leaves go down a cell a frame, and usually the way the wind's blowing.
+    fn blow_leaves(&mut self) {
+        let (min_corner, max_corner) = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let phase = self.phase();
+        let lie_for = self.period * SWAYS_ON_GROUND;
+        let frame = self.frame;
+
+        for leaf in self.loose_leaves.iter_mut() {
+            if leaf.landed.is_some() {
+                continue;
+            }
+
+            let drift = if phase > 0.3 {1} else if phase < -0.3 {-1} else {self.rng.gen_range(-1..=1)};
+            if self.rng.gen_bool(0.5) {
+                leaf.at.x = (leaf.at.x + drift).clamp(min_corner.x, max_corner.x);
+            }
+            leaf.at.y -= 1;
+            if leaf.at.y <= min_corner.y {
+                leaf.at.y = min_corner.y;
+                leaf.landed = Some(frame);
+            }
+        }
+
+        //Grow back the leaves that have lain on the ground long enough.
+        let (grown_back, still_loose): (Vec<LooseLeaf>, Vec<LooseLeaf>) = self.loose_leaves
+            .drain(..)
+            .partition(|leaf| leaf.landed.is_some_and(|landed| frame - landed >= lie_for));
+        self.loose_leaves = still_loose;
+        self.cells.extend(grown_back.iter().map(|leaf| (leaf.home, leaf.c)));
+    }
+}
+
+impl<R: Rng> Iterator for Sway<R> {
+    type Item = Fvtree;
+
+    fn next(&mut self) -> Option<Fvtree> {
+        if self.rng.gen_range(0..100) < self.leaf_fall_chance {
+            self.loosen_a_leaf();
+        }
+        self.blow_leaves();
+
+        let mut canvas = text_canvas::Canvas::new();
+        let mut taken = HashSet::new();
+        for &(point, c) in self.cells.iter() {
+            let point = Point {x: point.x + self.sway_at(point.y), y: point.y};
+            canvas.put(point, c);
+            taken.insert((point.x, point.y));
+        }
+        //Loose leaves go behind the tree, and each other.
+        for leaf in self.loose_leaves.iter() {
+            if taken.insert((leaf.at.x, leaf.at.y)) {
+                canvas.put(leaf.at, leaf.c);
+            }
+        }
+
+        if let Some((min_corner, max_corner)) = self.bounds {
+            canvas.extend_to(min_corner);
+            canvas.extend_to(max_corner);
+        }
+        self.frame += 1;
+
+        Some(Fvtree{canvas})
+    }
+}
+
+#[cfg(test)]
+mod wind_tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::{Fvtree, FvtreeString};
+
+    #[test]
+    fn swaying() {
+        let fvtree = Fvtree::build(&FvtreeString::build("v2:||||yo^l>l<lr").unwrap()).unwrap();
+        let still = fvtree.sway(&WindConfig {strength: 1, period: 4, leaf_fall_chance: 0}, StdRng::seed_from_u64(0)).next().unwrap().to_string();
+        let mut conf = WindConfig::new();
+        conf.period = 4;
+        conf.leaf_fall_chance = 100;
+
+        let mut sway = fvtree.sway(&conf, StdRng::seed_from_u64(0));
+        let frames: Vec<String> = sway.by_ref().take(40).map(|frame| frame.to_string()).collect();
+        let lines = frames[0].lines().count();
+        assert!(frames.iter().all(|frame| frame.lines().count() == lines));
+        //The root never moves, but the leaf spawn at the top does.
+        let root = |frame: &str| frame.lines().rev().nth(1).and_then(|line| line.find('O'));
+        let top = |frame: &str| frame.lines().find_map(|line| line.find('O'));
+        assert!(frames.iter().all(|frame| root(frame) == root(&still) && root(frame).is_some()));
+        assert!(frames.iter().any(|frame| top(frame) != top(&frames[0])));
+
+        //Leaves come loose and make it to the ground, though they can land behind the root.
+        let (min_corner, max_corner) = sway.bounds.unwrap();
+        let landed: Vec<&LooseLeaf> = sway.loose_leaves.iter().filter(|leaf| leaf.landed.is_some()).collect();
+        assert!(!landed.is_empty());
+        for leaf in landed {
+            assert_eq!(leaf.at.y, min_corner.y);
+            assert!(leaf.at.x >= min_corner.x && leaf.at.x <= max_corner.x);
+        }
+    }
+}
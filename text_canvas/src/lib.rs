@@ -2,6 +2,7 @@ use std::fmt;
 
 use common::Point;
 
+#[derive(Clone)]
 pub struct Canvas {
     canvas: Vec<Vec<char>>,
     //Change where the origin is located within the vec,
@@ -132,6 +133,107 @@ impl Canvas {
         row[x_index] = c;
     }
 
+    ///Make room for a point, without putting anything there, e.g. to keep canvases the same size.
+    ///#Panics
+    ///Panics if a point contains i32::MIN
+    pub fn extend_to(&mut self, point: Point) {
+        self.resize_y_axis_if_needed(point);
+        self.resize_x_axis_if_needed(point);
+    }
+
+    ///Every cell that isn't blank, and where it is.
+    pub fn cells(&self) -> Vec<(Point, char)> {
+        let mut cells = Vec::new();
+
+        for (y_index, row) in self.canvas.iter().enumerate() {
+            for (x_index, c) in row.iter().enumerate() {
+                if *c != ' ' {
+                    let point = Point {
+                        x: x_index as i32 - self.x_offset as i32,
+                        y: y_index as i32 - self.y_offset as i32,
+                    };
+                    cells.push((point, *c));
+                }
+            }
+        }
+
+        cells
+    }
+
+    ///The bottom left and top right corners of the canvas, or None if nothing's been put on it.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let widest = self.canvas.iter().map(|row| row.len()).max()?;
+        if widest == 0 {
+            return None;
+        }
+
+        let min_corner = Point {x: -(self.x_offset as i32), y: -(self.y_offset as i32)};
+        let max_corner = Point {
+            x: widest as i32 - 1 - self.x_offset as i32,
+            y: self.canvas.len() as i32 - 1 - self.y_offset as i32,
+        };
+
+        Some((min_corner, max_corner))
+    }
+
+    ///Mirror the canvas left to right about x = 0, turning glyphs like / and < around to match.
+    pub fn flip_horizontal(&mut self) {
+        self.transform(|point| Point {x: -point.x, y: point.y}, |c| match c {
+            '/' => '\\',
+            '\\' => '/',
+            '<' => '>',
+            '>' => '<',
+            '(' => ')',
+            ')' => '(',
+            '[' => ']',
+            ']' => '[',
+            '{' => '}',
+            '}' => '{',
+            c => c,
+        });
+    }
+
+    ///Turn the canvas upside down about y = 0, turning glyphs like / and ^ over to match.
+    pub fn flip_vertical(&mut self) {
+        self.transform(|point| Point {x: point.x, y: -point.y}, |c| match c {
+            '/' => '\\',
+            '\\' => '/',
+            '^' => 'v',
+            'v' => '^',
+            c => c,
+        });
+    }
+
+    ///Turn the canvas a quarter turn clockwise about the origin, turning glyphs like | and ^ with it.
+    pub fn rotate_90(&mut self) {
+        self.transform(|point| Point {x: point.y, y: -point.x}, |c| match c {
+            '|' => '-',
+            '-' => '|',
+            '/' => '\\',
+            '\\' => '/',
+            '^' => '>',
+            '>' => 'v',
+            'v' => '<',
+            '<' => '^',
+            c => c,
+        });
+    }
+
+    //Moves every cell, and the blank space around them, somewhere else.
+    fn transform(&mut self, move_point: impl Fn(Point) -> Point, glyph: impl Fn(char) -> char) {
+        let mut canvas = Canvas::new();
+
+        if let Some((min_corner, max_corner)) = self.bounds() {
+            canvas.extend_to(move_point(min_corner));
+            canvas.extend_to(move_point(max_corner));
+        }
+        for (point, c) in self.cells() {
+            canvas.put(move_point(point), glyph(c));
+        }
+
+        *self = canvas;
+    }
+
     //Check if point >= 0 when offset is added,
     //i.e., point values aren't negative for calc_vec_index.
     fn is_point_within_offset(&self, point: Point) -> bool {
@@ -332,6 +434,76 @@ mod text_canvas_tests {
         assert_eq!("z\ny\nx\n\n", output);
     }
 
+    #[test]
+    fn bounds() {
+        let mut canvas = Canvas::new();
+        assert_eq!(canvas.bounds(), None);
+
+        canvas.put(Point {x: -1, y: 2}, 'x');
+        canvas.put(Point {x: 3, y: -2}, 'y');
+        assert_eq!(canvas.bounds(), Some((Point {x: -1, y: -2}, Point {x: 3, y: 2})));
+    }
+
+    #[test]
+    fn cells() {
+        let mut canvas = Canvas::new();
+        canvas.put(Point {x: 1, y: 1}, 'x');
+        canvas.put(Point {x: -1, y: -1}, 'y');
+        canvas.put(Point {x: 0, y: 0}, ' ');
+        assert_eq!(canvas.cells(), vec![(Point {x: -1, y: -1}, 'y'), (Point {x: 1, y: 1}, 'x')]);
+    }
+
+    #[test]
+    fn extend_to() {
+        let mut canvas = Canvas::new();
+        canvas.put(Point {x: 0, y: 0}, 'x');
+        canvas.extend_to(Point {x: -1, y: -1});
+        canvas.extend_to(Point {x: 1, y: 1});
+        let output = format!("{}", canvas);
+        assert_eq!("   \n x\n \n", output);
+        assert_eq!(canvas.bounds(), Some((Point {x: -1, y: -1}, Point {x: 1, y: 1})));
+    }
+
+    #[test]
+    fn flips() {
+        let mut canvas = Canvas::new();
+        canvas.put(Point {x: 0, y: 0}, '|');
+        canvas.put(Point {x: 1, y: 1}, '/');
+        canvas.put(Point {x: 0, y: -1}, '^');
+        canvas.extend_to(Point {x: 2, y: 0});
+
+        let mut flipped = canvas.clone();
+        flipped.flip_horizontal();
+        assert_eq!(flipped.cells(), vec![(Point {x: 0, y: -1}, '^'), (Point {x: 0, y: 0}, '|'), (Point {x: -1, y: 1}, '\\')]);
+        assert_eq!(flipped.bounds(), Some((Point {x: -2, y: -1}, Point {x: 0, y: 1})));
+        flipped.flip_horizontal();
+        assert_eq!(flipped.cells(), canvas.cells());
+        assert_eq!(flipped.bounds(), canvas.bounds());
+
+        let mut flipped = canvas.clone();
+        flipped.flip_vertical();
+        assert_eq!(flipped.cells(), vec![(Point {x: 1, y: -1}, '\\'), (Point {x: 0, y: 0}, '|'), (Point {x: 0, y: 1}, 'v')]);
+    }
+
+    #[test]
+    fn rotate_90() {
+        let mut canvas = Canvas::new();
+        canvas.put(Point {x: 0, y: 0}, '|');
+        canvas.put(Point {x: 0, y: 1}, '^');
+        canvas.put(Point {x: 1, y: 1}, '/');
+
+        canvas.rotate_90();
+        assert_eq!(format!("{}", canvas), "->\n \\\n");
+
+        //All the way around.
+        let mut turned = canvas.clone();
+        for _ in 0..4 {
+            turned.rotate_90();
+        }
+        assert_eq!(turned.cells(), canvas.cells());
+        assert_eq!(turned.bounds(), canvas.bounds());
+    }
+
     #[test]
     fn char_is_present_check() {
         let mut canvas = Canvas::new();